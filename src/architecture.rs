@@ -0,0 +1,337 @@
+use crate::{CircuitConfig, Control};
+use serde::{Deserialize, Serialize};
+
+/// Where the signals and OLMCs of a device are in its fuse array. The array is made of rows of
+/// `row_len` fuses, row `n` starts at fuse `n * row_len`. A product term is programmed by clearing
+/// the column of every signal it reads. The keys of a device file can be spelled like the fields
+/// or like `RowLength` etc.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Architecture {
+    #[serde(alias = "RowLength")]
+    pub row_len: u32,
+    /// the column of every input and feedback signal as (pin, column), the column after it reads
+    /// the inverted signal
+    #[serde(alias = "Columns")]
+    pub columns: Vec<(u32, u32)>,
+    #[serde(alias = "Olmcs")]
+    pub olmcs: Vec<Olmc>,
+    /// the asynchronous reset row, it is left cleared
+    #[serde(alias = "ArRow")]
+    pub ar_row: Option<u32>,
+    /// the synchronous preset row, it is left cleared
+    #[serde(alias = "SpRow")]
+    pub sp_row: Option<u32>,
+    /// fuse ranges as (first, last, value) which are the same for every design e.g. the mode of
+    /// the whole device
+    #[serde(default, alias = "Fixed")]
+    pub fixed: Vec<(u32, u32, bool)>,
+}
+
+/// the rows and mode fuses of one output cell
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Olmc {
+    #[serde(alias = "Pin")]
+    pub pin: u32,
+    /// the output enable row, it is set to always enabled if the output is driven
+    #[serde(alias = "OeRow")]
+    pub oe_row: Option<u32>,
+    /// the row of the clock term of the register, on a device without a clock pin a registered
+    /// output needs `.clk`
+    #[serde(default, alias = "ClkRow")]
+    pub clk_row: Option<u32>,
+    /// the asynchronous reset row of the register, it is left cleared without `.ar`
+    #[serde(default, alias = "ArRow")]
+    pub ar_row: Option<u32>,
+    /// the asynchronous preset row of the register, it is left cleared without `.ap`
+    #[serde(default, alias = "ApRow")]
+    pub ap_row: Option<u32>,
+    /// the first product term row, the other terms follow it
+    #[serde(alias = "FirstRow")]
+    pub first_row: u32,
+    #[serde(alias = "Terms")]
+    pub terms: u32,
+    /// `None` if the OLMC can't be combinational in this layout
+    #[serde(alias = "Combinational")]
+    pub combinational: Option<Mode>,
+    /// `None` if the OLMC has no register in this layout
+    #[serde(alias = "Registered")]
+    pub registered: Option<Mode>,
+    /// the mode of an OLMC which isn't driven, its pin can be used as an input
+    #[serde(default, alias = "Input")]
+    pub input: Mode,
+}
+
+/// how an OLMC is switched into a mode
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Mode {
+    /// the mode fuses and the value they are programmed to
+    #[serde(default, alias = "Fuses")]
+    pub fuses: Vec<(u32, bool)>,
+    /// the feedback column reads the inverted output in this mode e.g. because it comes from /Q
+    #[serde(default, alias = "FeedbackInverted")]
+    pub feedback_inverted: bool,
+    /// the output is enabled by a pin instead of the OE row, the row is left cleared
+    #[serde(default, alias = "ExternalOe")]
+    pub external_oe: bool,
+    /// rows which are set to a constant 1 in this mode e.g. the reset and preset rows of a
+    /// 20RA10 bypass its register if both are 1
+    #[serde(default, alias = "SetRows")]
+    pub set_rows: Vec<u32>,
+    /// the fuses which clock the register by its `.clk` term instead of the clock pin
+    #[serde(default, alias = "TermClock")]
+    pub term_clock: Vec<(u32, bool)>,
+    /// the output inverts the sum of the terms e.g. on a PAL16L8, the terms are built for the
+    /// inverted table
+    #[serde(default, alias = "ActiveLow")]
+    pub active_low: bool,
+    /// the first term row if it differs in this mode e.g. a registered output of a V8 in
+    /// registered mode is enabled by a pin and uses its OE row as a term
+    #[serde(default, alias = "FirstRow")]
+    pub first_row: Option<u32>,
+    /// the number of terms if it differs in this mode
+    #[serde(default, alias = "Terms")]
+    pub terms: Option<u32>,
+}
+
+impl Architecture {
+    /// The layout of a 22V10 style config without a description. Every input pin `n` has the
+    /// column `(n - 1) * 4` and the OLMCs follow the AR row from the last output to the first one,
+    /// each with its OE row before the terms. The SP row and two mode fuses per OLMC come last.
+    pub fn derive(config: &CircuitConfig) -> Architecture {
+        let row_len = (config.inputs.len() + config.special_pins.len()) as u32 * 2;
+        let num_outputs = config.outputs.len() as u32;
+
+        let mut columns: Vec<(u32, u32)> = config.special_pins.clone();
+        for &pin in config.inputs.iter() {
+            let column = match config.outputs.iter().position(|&(out, _)| out == pin) {
+                Some(i) => 2 + (num_outputs - 1 - i as u32) * 4,
+                None => (pin - 1) * 4,
+            };
+            columns.push((pin, column));
+        }
+        columns.sort_unstable_by_key(|&(_, column)| column);
+
+        let mut row = 1;
+        let mut olmcs = Vec::new();
+        for &(pin, terms) in config.outputs.iter().rev() {
+            olmcs.push(Olmc {
+                pin,
+                oe_row: Some(row),
+                clk_row: None,
+                ar_row: None,
+                ap_row: None,
+                first_row: row + 1,
+                terms,
+                combinational: None,
+                registered: None,
+                input: Mode::default(),
+            });
+            row += terms + 1;
+        }
+
+        let mode_fuses = (row + 1) * row_len;
+        for (i, olmc) in olmcs.iter_mut().enumerate() {
+            let s0 = mode_fuses + i as u32 * 2;
+            olmc.combinational = Some(Mode {
+                fuses: vec![(s0, true), (s0 + 1, true)],
+                ..Mode::default()
+            });
+            olmc.registered = Some(Mode {
+                fuses: vec![(s0, true), (s0 + 1, false)],
+                feedback_inverted: true,
+                ..Mode::default()
+            });
+        }
+
+        Architecture {
+            row_len,
+            columns,
+            olmcs,
+            ar_row: Some(0),
+            sp_row: Some(row),
+            fixed: Vec::new(),
+        }
+    }
+
+    pub fn olmc(&self, pin: u32) -> Option<&Olmc> {
+        self.olmcs.iter().find(|olmc| olmc.pin == pin)
+    }
+
+    pub fn column(&self, pin: u32) -> Option<u32> {
+        self.columns
+            .iter()
+            .find(|&&(p, _)| p == pin)
+            .map(|&(_, column)| column)
+    }
+
+    /// checks that the OLMCs match the outputs of the config and every row, column and mode fuse
+    /// is inside the fuse array
+    pub fn validate(&self, config: &CircuitConfig) -> Result<(), String> {
+        let num_rows = config.num_fuses / self.row_len.max(1);
+        for &(pin, column) in self.columns.iter() {
+            if column + 1 >= self.row_len {
+                return Err(format!(
+                    "column {} of pin {} is outside of the {} columns of a row",
+                    column, pin, self.row_len
+                ));
+            }
+        }
+        for &(pin, terms) in config.outputs.iter() {
+            match self.olmc(pin) {
+                Some(olmc) if olmc.terms == terms => (),
+                Some(olmc) => {
+                    return Err(format!(
+                        "output pin {} has {} terms but its OLMC has {}",
+                        pin, terms, olmc.terms
+                    ))
+                }
+                None => return Err(format!("output pin {} has no OLMC", pin)),
+            }
+        }
+
+        let mut rows: Vec<(u32, u32, String)> = Vec::new();
+        rows.extend(self.ar_row.map(|row| (row, row, "the AR row".to_string())));
+        rows.extend(self.sp_row.map(|row| (row, row, "the SP row".to_string())));
+        for olmc in self.olmcs.iter() {
+            if olmc.terms == 0 {
+                return Err(format!("the OLMC of pin {} has no terms", olmc.pin));
+            }
+            for &control in Control::ALL.iter() {
+                if let Some(row) = olmc.control_row(control) {
+                    let name = format!("the {} row of pin {}", control.name(), olmc.pin);
+                    rows.push((row, row, name));
+                }
+            }
+            rows.push((
+                olmc.first_row,
+                olmc.first_row + olmc.terms - 1,
+                format!("the terms of pin {}", olmc.pin),
+            ));
+        }
+        // a mode may move the terms onto its own control rows, so they are only checked to fit
+        for olmc in self.olmcs.iter() {
+            let (first, terms) = olmc.term_rows(true);
+            if (first, terms) != (olmc.first_row, olmc.terms) && first + terms > num_rows {
+                return Err(format!(
+                    "the registered terms of pin {} can't be in row {}, the device has {} rows",
+                    olmc.pin,
+                    first + terms - 1,
+                    num_rows
+                ));
+            }
+        }
+        for (i, (first, last, name)) in rows.iter().enumerate() {
+            if *last >= num_rows {
+                return Err(format!(
+                    "{} can't be in row {}, the device has {} rows",
+                    name, last, num_rows
+                ));
+            }
+            if let Some((_, _, other)) = rows[..i]
+                .iter()
+                .find(|(start, end, _)| first <= end && start <= last)
+            {
+                return Err(format!("{} and {} share a row", other, name));
+            }
+        }
+
+        for &(first, last, _) in self.fixed.iter() {
+            if first > last || last >= config.num_fuses {
+                return Err(format!(
+                    "the fixed fuses {}..={} are outside of the {} fuses",
+                    first, last, config.num_fuses
+                ));
+            }
+        }
+        for olmc in self.olmcs.iter() {
+            let modes = [&olmc.combinational, &olmc.registered];
+            let modes = modes
+                .iter()
+                .filter_map(|mode| mode.as_ref())
+                .chain(Some(&olmc.input));
+            for &row in modes.clone().flat_map(|mode| mode.set_rows.iter()) {
+                if row >= num_rows {
+                    return Err(format!(
+                        "row {} set by pin {} is outside of the {} rows",
+                        row, olmc.pin, num_rows
+                    ));
+                }
+            }
+            let fuses = modes.flat_map(|mode| mode.fuses.iter().chain(mode.term_clock.iter()));
+            for &(fuse, _) in fuses {
+                let row = fuse / self.row_len.max(1);
+                if fuse >= config.num_fuses {
+                    return Err(format!(
+                        "mode fuse {} of pin {} is outside of the {} fuses",
+                        fuse, olmc.pin, config.num_fuses
+                    ));
+                } else if let Some((_, _, name)) = rows
+                    .iter()
+                    .find(|(start, end, _)| *start <= row && row <= *end)
+                {
+                    return Err(format!(
+                        "mode fuse {} of pin {} is in {}",
+                        fuse, olmc.pin, name
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Olmc {
+    /// the mode the OLMC is in if its output is registered or not, `None` if it can't be
+    pub fn mode(&self, registered: bool) -> Option<&Mode> {
+        if registered {
+            self.registered.as_ref()
+        } else {
+            self.combinational.as_ref()
+        }
+    }
+
+    /// the first term row and the number of terms in a mode, see `Mode::first_row`
+    pub fn term_rows(&self, registered: bool) -> (u32, u32) {
+        let mode = self.mode(registered);
+        (
+            mode.and_then(|mode| mode.first_row)
+                .unwrap_or(self.first_row),
+            mode.and_then(|mode| mode.terms).unwrap_or(self.terms),
+        )
+    }
+
+    /// the row the product term of a control is written to
+    pub fn control_row(&self, control: Control) -> Option<u32> {
+        match control {
+            Control::Oe => self.oe_row,
+            Control::Clk => self.clk_row,
+            Control::Ar => self.ar_row,
+            Control::Ap => self.ap_row,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CircuitConfig;
+
+    #[test]
+    fn validate() {
+        let config = CircuitConfig::new(64, 8, vec![1, 2, 3], vec![(3, 2)], Vec::new());
+        let mut architecture = config.architecture();
+        assert_eq!(architecture.column(3), Some(2));
+        assert_eq!(architecture.olmcs[0].first_row, 2);
+        assert_eq!(architecture.validate(&config), Ok(()));
+
+        architecture.olmcs[0].first_row = 3;
+        assert_eq!(
+            architecture.validate(&config),
+            Err("the SP row and the terms of pin 3 share a row".to_string())
+        );
+        architecture.olmcs[0].first_row = 9;
+        assert_eq!(
+            architecture.validate(&config),
+            Err("the terms of pin 3 can't be in row 10, the device has 10 rows".to_string())
+        );
+    }
+}
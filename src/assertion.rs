@@ -0,0 +1,304 @@
+use crate::simulator::Simulator;
+use crate::OGal;
+use hardware_sim::{Component, LookupTable};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Checking more free signals than this would take too long to be done on every build.
+const MAX_FREE_SIGNALS: usize = 24;
+
+/// What an assertion claims about its expression.
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Property {
+    /// `assert expr;` holds for every combination of inputs and register states
+    Invariant,
+    /// `assert always (expr);` holds in every state reachable from power-up,
+    /// optionally only for the first `within k` clock cycles
+    Always(Option<usize>),
+    /// `assert eventually (expr) within k;` becomes true within k clock cycles for every
+    /// sequence of inputs starting at power-up
+    Eventually(usize),
+}
+
+/// An `assert` statement which is checked on every build.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Assertion {
+    lut: LookupTable,
+    source: String,
+    property: Property,
+}
+
+impl Assertion {
+    pub fn new(lut: LookupTable, source: String, property: Property) -> Self {
+        Self {
+            lut,
+            source,
+            property,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn property(&self) -> Property {
+        self.property
+    }
+
+    /// returns an error with a counterexample if the assertion doesn't hold
+    pub fn check(&self, o_gal: &OGal) -> Result<(), String> {
+        match self.property {
+            Property::Invariant => match self.counterexample(o_gal)? {
+                None => Ok(()),
+                Some(assignment) => Err(format!(
+                    "assert {} doesn't hold for {}",
+                    self.source,
+                    format_assignment(&assignment)
+                )),
+            },
+            Property::Always(bound) => match Machine::new(self, o_gal)?.always(bound)? {
+                None => Ok(()),
+                Some(trace) => Err(format!(
+                    "assert always {} fails after {} clock cycles:\n{}",
+                    self.source,
+                    trace.len() - 1,
+                    trace.join("\n")
+                )),
+            },
+            Property::Eventually(bound) => match Machine::new(self, o_gal)?.eventually(bound)? {
+                None => Ok(()),
+                Some(trace) => Err(format!(
+                    "assert eventually {} doesn't hold within {} clock cycles:\n{}",
+                    self.source,
+                    bound,
+                    trace.join("\n")
+                )),
+            },
+        }
+    }
+
+    /// searches all combinations of the free signals (inputs and register outputs) the
+    /// expression depends on and returns the first one for which the expression isn't true
+    pub fn counterexample(&self, o_gal: &OGal) -> Result<Option<Vec<(String, bool)>>, String> {
+        let free = free_signals(&self.lut.in_names(), o_gal);
+        if free.len() > MAX_FREE_SIGNALS {
+            return Err(format!(
+                "assert {} depends on {} signals, at most {} can be checked",
+                self.source,
+                free.len(),
+                MAX_FREE_SIGNALS
+            ));
+        }
+
+        let table = &self.lut.get_table()[0];
+        let mut sim = Simulator::new(o_gal);
+        for combination in 0..(1_usize << free.len()) {
+            for (i, name) in free.iter().enumerate() {
+                sim.force(name, Some(combination & (1 << (free.len() - 1 - i)) != 0));
+            }
+            sim.step(&HashMap::new(), false)?;
+
+            if sim.eval(&self.lut.in_names(), table) != Some(true) {
+                let assignment = free
+                    .iter()
+                    .map(|name| (name.clone(), sim.get(name) == Some(true)))
+                    .collect();
+                return Ok(Some(assignment));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// The registered design as a transition relation, states and inputs are stored as bits
+/// in the order of `registers` and `inputs`.
+struct Machine<'a> {
+    assertion: &'a Assertion,
+    sim: Simulator<'a>,
+    registers: Vec<String>,
+    inputs: Vec<String>,
+}
+
+impl<'a> Machine<'a> {
+    fn new(assertion: &'a Assertion, o_gal: &'a OGal) -> Result<Self, String> {
+        let mut registers: Vec<String> = o_gal
+            .dff
+            .iter()
+            .filter(|name| o_gal.lut.iter().any(|lut| lut.out_names().contains(name)))
+            .cloned()
+            .collect();
+        registers.sort();
+        registers.dedup();
+
+        let mut names = assertion.lut.in_names();
+        for lut in o_gal.lut.iter() {
+            if lut.out_names().iter().any(|name| registers.contains(name)) {
+                names.append(&mut lut.in_names());
+            }
+        }
+        let inputs: Vec<String> = free_signals(&names, o_gal)
+            .into_iter()
+            .filter(|name| !registers.contains(name))
+            .collect();
+
+        if registers.len() + inputs.len() > MAX_FREE_SIGNALS {
+            return Err(format!(
+                "assert {} depends on {} registers and {} inputs, at most {} signals can be checked",
+                assertion.source,
+                registers.len(),
+                inputs.len(),
+                MAX_FREE_SIGNALS
+            ));
+        }
+
+        Ok(Self {
+            assertion,
+            sim: Simulator::new(o_gal),
+            registers,
+            inputs,
+        })
+    }
+
+    /// explores the reachable states breadth first and returns the trace to the first
+    /// state and input in which the expression isn't true
+    fn always(&mut self, bound: Option<usize>) -> Result<Option<Vec<String>>, String> {
+        let mut visited = HashSet::new();
+        visited.insert(0);
+        let mut frontier = vec![(0, Vec::new())];
+        let mut cycle = 0;
+
+        while !frontier.is_empty() && cycle <= bound.unwrap_or(usize::MAX) {
+            let mut next_frontier = Vec::new();
+            for (state, trace) in frontier {
+                for input in 0..(1_usize << self.inputs.len()) {
+                    let (holds, next) = self.cycle(state, input)?;
+                    if holds != Some(true) {
+                        let mut trace = trace.clone();
+                        trace.push((state, input));
+                        return Ok(Some(self.format_trace(&trace)));
+                    }
+                    if visited.insert(next) {
+                        let mut trace = trace.clone();
+                        trace.push((state, input));
+                        next_frontier.push((next, trace));
+                    }
+                }
+            }
+            frontier = next_frontier;
+            cycle += 1;
+        }
+
+        Ok(None)
+    }
+
+    /// follows every input sequence on which the expression hasn't been true yet and returns
+    /// the first one that is still pending after `bound` clock cycles
+    fn eventually(&mut self, bound: usize) -> Result<Option<Vec<String>>, String> {
+        let mut pending = vec![(0, Vec::new())];
+
+        for cycle in 0..=bound {
+            let mut next_pending = HashMap::new();
+            for (state, trace) in pending {
+                for input in 0..(1_usize << self.inputs.len()) {
+                    let (holds, next) = self.cycle(state, input)?;
+                    if holds == Some(true) {
+                        continue;
+                    }
+
+                    let mut trace: Vec<(usize, usize)> = trace.clone();
+                    trace.push((state, input));
+                    if cycle == bound {
+                        return Ok(Some(self.format_trace(&trace)));
+                    }
+                    next_pending.entry(next).or_insert(trace);
+                }
+            }
+
+            if next_pending.is_empty() {
+                return Ok(None);
+            }
+            pending = next_pending.into_iter().collect();
+        }
+
+        Ok(None)
+    }
+
+    /// evaluates the expression in the given state and returns it together with the next state
+    fn cycle(&mut self, state: usize, input: usize) -> Result<(Option<bool>, usize), String> {
+        for (i, name) in self.registers.iter().enumerate() {
+            self.sim.force(name, Some(state & (1 << i) != 0));
+        }
+        for (i, name) in self.inputs.iter().enumerate() {
+            self.sim.force(name, Some(input & (1 << i) != 0));
+        }
+        self.sim.step(&HashMap::new(), false)?;
+        let holds = self.sim.eval(
+            &self.assertion.lut.in_names(),
+            &self.assertion.lut.get_table()[0],
+        );
+
+        self.sim.step(&HashMap::new(), true)?;
+        let mut next = 0;
+        for (i, name) in self.registers.iter().enumerate() {
+            match self.sim.get(name) {
+                Some(true) => next |= 1 << i,
+                Some(false) => (),
+                None => return Err(format!("register {} has an unknown next state", name)),
+            }
+        }
+
+        Ok((holds, next))
+    }
+
+    fn format_trace(&self, trace: &[(usize, usize)]) -> Vec<String> {
+        let mut result = Vec::new();
+        for (cycle, &(state, input)) in trace.iter().enumerate() {
+            let mut assignment = Vec::new();
+            for (i, name) in self.registers.iter().enumerate() {
+                assignment.push((name.clone(), state & (1 << i) != 0));
+            }
+            for (i, name) in self.inputs.iter().enumerate() {
+                assignment.push((name.clone(), input & (1 << i) != 0));
+            }
+            result.push(format!(
+                "  cycle {}: {}",
+                cycle,
+                format_assignment(&assignment)
+            ));
+        }
+        result
+    }
+}
+
+/// follows the combinational logic back from `names` and returns every signal which is
+/// either an input or the output of a register
+pub fn free_signals(names: &[String], o_gal: &OGal) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut visited = Vec::new();
+    let mut todo: Vec<String> = names.to_vec();
+
+    while let Some(name) = todo.pop() {
+        if visited.contains(&name) {
+            continue;
+        }
+        visited.push(name.clone());
+
+        let driver = o_gal.lut.iter().find(|lut| lut.out_names().contains(&name));
+        match driver {
+            Some(lut) if !o_gal.dff.contains(&name) => todo.append(&mut lut.in_names()),
+            _ => result.push(name),
+        }
+    }
+
+    result.sort();
+    result
+}
+
+pub fn format_assignment(assignment: &[(String, bool)]) -> String {
+    assignment
+        .iter()
+        .map(|(name, val)| format!("{} = {}", name, *val as u8))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
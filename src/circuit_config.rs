@@ -1,12 +1,33 @@
+use crate::Architecture;
 use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
 
+/// The pins and fuses of a device. It can be read from a device file like `Configs/g22v10.json`
+/// where the keys are spelled `NumFuses`, `TotalNumPins`, `InputPins`, `OutputPins` and
+/// `SpecialPins`, see `CircuitConfig::from_json`.
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct CircuitConfig {
+    #[serde(alias = "NumFuses")]
     pub num_fuses: u32,
+    #[serde(alias = "TotalNumPins")]
     pub num_pins: u32,
+    #[serde(alias = "InputPins")]
     pub inputs: Vec<u32>,
+    #[serde(alias = "OutputPins")]
     pub outputs: Vec<(u32, u32)>,
+    #[serde(alias = "SpecialPins")]
     pub special_pins: Vec<(u32, u32)>,
+    /// the pin which clocks the registers, the fitter keeps it free in designs with `.dff`
+    #[serde(default, alias = "ClockPin")]
+    pub clock: Option<u32>,
+    /// the numbers of the buried OLMCs, they have no pin and are numbered after the pins. They are
+    /// listed in `inputs` and `outputs` like an I/O pin and only get signals declared with `node`.
+    #[serde(default, alias = "Nodes")]
+    pub nodes: Vec<u32>,
+    /// where the pins and OLMCs are in the fuse array, see `CircuitConfig::architecture`
+    #[serde(default, alias = "Architecture")]
+    pub architecture: Option<Architecture>,
 }
 
 impl CircuitConfig {
@@ -23,6 +44,120 @@ impl CircuitConfig {
             inputs,
             outputs,
             special_pins,
+            clock: None,
+            nodes: Vec::new(),
+            architecture: None,
         }
     }
+
+    pub fn with_clock(mut self, pin: u32) -> Self {
+        self.clock = Some(pin);
+        self
+    }
+
+    pub fn with_architecture(mut self, architecture: Architecture) -> Self {
+        self.architecture = Some(architecture);
+        self
+    }
+
+    /// the described architecture or the 22V10 style one derived from the pins
+    pub fn architecture(&self) -> Architecture {
+        match &self.architecture {
+            Some(architecture) => architecture.clone(),
+            None => Architecture::derive(self),
+        }
+    }
+
+    /// the product terms of the output `pin` if it is registered or not, a mode can have more
+    /// terms than `outputs` says (see `Mode::terms`)
+    pub fn terms(&self, pin: u32, registered: bool) -> Option<u32> {
+        let &(_, terms) = self.outputs.iter().find(|&&(out, _)| out == pin)?;
+        let architecture = self.architecture();
+        match architecture
+            .olmc(pin)
+            .and_then(|olmc| olmc.mode(registered))
+        {
+            Some(mode) => Some(mode.terms.unwrap_or(terms)),
+            None => Some(terms),
+        }
+    }
+
+    /// parses and validates a device file, the keys can be spelled like the fields or like the
+    /// shipped files e.g. `num_fuses` or `NumFuses`
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let config: CircuitConfig = match serde_json::from_str(json) {
+            Ok(config) => config,
+            Err(err) => return Err(format!("invalid device file: {}", err)),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(err) => return Err(format!("can't read {}: {}", path.display(), err)),
+        };
+        match Self::from_json(&json) {
+            Ok(config) => Ok(config),
+            Err(msg) => Err(format!("{}: {}", path.display(), msg)),
+        }
+    }
+
+    /// Checks that every pin is on the device, no pin has two roles and the architecture fits
+    /// into `num_fuses`. Pins which are in `inputs` and `outputs` are I/O pins.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut pins: Vec<u32> = self.inputs.clone();
+        pins.extend(self.outputs.iter().map(|&(pin, _)| pin));
+        pins.extend(self.special_pins.iter().map(|&(pin, _)| pin));
+        pins.extend(self.clock);
+        if let Some(pin) = pins
+            .iter()
+            .find(|&&pin| pin == 0 || (pin > self.num_pins && !self.nodes.contains(&pin)))
+        {
+            return Err(format!(
+                "pin {} is out of range, the device has {} pins",
+                pin, self.num_pins
+            ));
+        }
+        if let Some(node) = self.nodes.iter().find(|&&node| node <= self.num_pins) {
+            return Err(format!(
+                "node {} has the number of a pin, nodes are numbered after the {} pins",
+                node, self.num_pins
+            ));
+        }
+
+        let roles: [(&str, Vec<u32>); 3] = [
+            ("input", self.inputs.clone()),
+            ("output", self.outputs.iter().map(|&(pin, _)| pin).collect()),
+            (
+                "special pin",
+                self.special_pins.iter().map(|&(pin, _)| pin).collect(),
+            ),
+        ];
+        for (role, pins) in roles.iter() {
+            for (i, pin) in pins.iter().enumerate() {
+                if pins[..i].contains(pin) {
+                    return Err(format!("pin {} is listed twice as {}", pin, role));
+                }
+            }
+        }
+        for &(pin, _) in self.special_pins.iter() {
+            if let Some((role, _)) = roles[..2].iter().find(|(_, pins)| pins.contains(&pin)) {
+                return Err(format!("pin {} is both a special pin and an {}", pin, role));
+            }
+        }
+        if let Some(clock) = self.clock {
+            if self.outputs.iter().any(|&(pin, _)| pin == clock) {
+                return Err(format!("the clock pin {} is an output", clock));
+            }
+        }
+
+        if let Some(&(pin, _)) = self.outputs.iter().find(|&&(_, terms)| terms == 0) {
+            return Err(format!("output pin {} has no terms", pin));
+        }
+        self.architecture().validate(self)?;
+        Ok(())
+    }
 }
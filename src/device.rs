@@ -0,0 +1,462 @@
+//! The parts open-gal can program and picking the smallest one a design fits into.
+
+use crate::translator::dnf::Expression;
+use crate::translator::split::split;
+use crate::{to_jedec, to_jedec_with_vectors, CircuitConfig, Control, OGal, Olmc};
+use std::sync::OnceLock;
+
+/// a part open-gal can program
+#[derive(PartialEq, Debug, Clone)]
+pub struct Device {
+    pub name: &'static str,
+    /// other part numbers of the same fuse map e.g. from other vendors
+    pub aliases: &'static [&'static str],
+    /// the mode of the whole device the config is for e.g. `simple`, a device with modes is
+    /// listed once per mode
+    pub mode: Option<&'static str>,
+    pub packages: Vec<Package>,
+    pub vcc: u32,
+    pub gnd: u32,
+    pub config: CircuitConfig,
+}
+
+/// A housing of a device. The pins of the design and the config are numbered like the DIP, `pins`
+/// maps them to the pins of the package.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Package {
+    pub name: &'static str,
+    pub pins: Vec<u32>,
+}
+
+impl Package {
+    /// the pin of the package which is pin `pin` of the DIP
+    pub fn pin(&self, pin: u32) -> Option<u32> {
+        self.pins.get((pin as usize).checked_sub(1)?).cloned()
+    }
+}
+
+/// what a pin of a device is used for
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum PinRole {
+    /// an input which also clocks the registers
+    Clock,
+    Input,
+    /// an OLMC with the number of product terms it has
+    Output(u32),
+    /// an input with its column in the fuse array, see `CircuitConfig::special_pins`
+    Special(u32),
+    Vcc,
+    Gnd,
+    Unused,
+}
+
+impl Device {
+    /// every known device, the smallest first, the device files are only parsed once
+    pub fn all() -> &'static [Device] {
+        static DEVICES: OnceLock<Vec<Device>> = OnceLock::new();
+        DEVICES.get_or_init(|| {
+            // The V8 parts switch the mode of all OLMCs at once. In registered mode a registered OLMC
+            // is enabled by pin 11 or 13 and uses its OE row as an eighth term.
+            let gal16v8 = |mode, json| Device {
+                name: "GAL16V8",
+                aliases: &["ATF16V8B", "ATF16V8C", "PALCE16V8"],
+                mode: Some(mode),
+                packages: vec![
+                    dip(20),
+                    Package {
+                        name: "PLCC20",
+                        pins: (1..=20).collect(),
+                    },
+                ],
+                vcc: 20,
+                gnd: 10,
+                config: device_file(json),
+            };
+            // The PALs have a fixed architecture, every OLMC is either registered or combinational
+            // and their outputs are active low.
+            let pal16 = |name, json| Device {
+                name,
+                aliases: &[],
+                mode: None,
+                packages: vec![
+                    dip(20),
+                    Package {
+                        name: "PLCC20",
+                        pins: (1..=20).collect(),
+                    },
+                ],
+                vcc: 20,
+                gnd: 10,
+                config: device_file(json),
+            };
+            let gal20v8 = |mode, json| Device {
+                name: "GAL20V8",
+                aliases: &["ATF20V8B", "PALCE20V8"],
+                mode: Some(mode),
+                packages: vec![dip(24), plcc28()],
+                vcc: 24,
+                gnd: 12,
+                config: device_file(json),
+            };
+            vec![
+                gal16v8("simple", include_str!("../Configs/g16v8_simple.json")),
+                gal16v8("complex", include_str!("../Configs/g16v8_complex.json")),
+                gal16v8(
+                    "registered",
+                    include_str!("../Configs/g16v8_registered.json"),
+                ),
+                gal20v8("simple", include_str!("../Configs/g20v8_simple.json")),
+                gal20v8("complex", include_str!("../Configs/g20v8_complex.json")),
+                gal20v8(
+                    "registered",
+                    include_str!("../Configs/g20v8_registered.json"),
+                ),
+                // every OLMC has its own clock, reset, preset and OE term, pin 1 is /PL and 13 is /OE
+                Device {
+                    name: "GAL20RA10",
+                    aliases: &[],
+                    mode: None,
+                    packages: vec![dip(24), plcc28()],
+                    vcc: 24,
+                    gnd: 12,
+                    config: device_file(include_str!("../Configs/g20ra10.json")),
+                },
+                Device {
+                    name: "GAL22V10",
+                    aliases: &["ATF22V10B", "ATF22V10C", "PALCE22V10"],
+                    mode: None,
+                    packages: vec![dip(24), plcc28()],
+                    vcc: 24,
+                    gnd: 12,
+                    config: device_file(include_str!("../Configs/g22v10.json")),
+                },
+                // the PLCC has 28 pins as well and the same numbering as the DIP
+                Device {
+                    name: "GAL26CV12",
+                    aliases: &[],
+                    mode: None,
+                    packages: vec![
+                        dip(28),
+                        Package {
+                            name: "PLCC28",
+                            pins: (1..=28).collect(),
+                        },
+                    ],
+                    vcc: 7,
+                    gnd: 21,
+                    config: device_file(include_str!("../Configs/g26cv12.json")),
+                },
+                // Every macrocell has a second register without a pin, the nodes 25 to 34 belong to
+                // the pins 14 to 23. Both registers of a macrocell share the fuse which selects the
+                // clock pin or their `.clk` terms.
+                Device {
+                    name: "ATF750C",
+                    aliases: &["ATF750CL"],
+                    mode: None,
+                    packages: vec![dip(24), plcc28()],
+                    vcc: 24,
+                    gnd: 12,
+                    config: device_file(include_str!("../Configs/atf750c.json")),
+                },
+                // A GAL16V8 replaces all of them, so they come last and are only picked by a
+                // `device` statement.
+                pal16("PAL16L8", include_str!("../Configs/pal16l8.json")),
+                pal16("PAL16R4", include_str!("../Configs/pal16r4.json")),
+                pal16("PAL16R6", include_str!("../Configs/pal16r6.json")),
+                pal16("PAL16R8", include_str!("../Configs/pal16r8.json")),
+            ]
+        })
+    }
+
+    /// the name and the mode e.g. `GAL16V8 simple`
+    pub fn full_name(&self) -> String {
+        match self.mode {
+            Some(mode) => format!("{} {}", self.name, mode),
+            None => self.name.to_string(),
+        }
+    }
+
+    /// finds a device by its name or one of its aliases, ignoring case, in its first mode, see
+    /// `OGal::select_mode` for the mode a design needs
+    pub fn by_name(name: &str) -> Option<Device> {
+        Device::modes(name).into_iter().next()
+    }
+
+    /// every mode of the device with this name or alias
+    pub fn modes(name: &str) -> Vec<Device> {
+        Device::all()
+            .iter()
+            .filter(|device| {
+                device.name.eq_ignore_ascii_case(name)
+                    || device
+                        .aliases
+                        .iter()
+                        .any(|alias| alias.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn package(&self, name: &str) -> Option<&Package> {
+        self.packages
+            .iter()
+            .find(|package| package.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn pin_role(&self, pin: u32) -> PinRole {
+        let config = &self.config;
+        if pin == self.vcc {
+            PinRole::Vcc
+        } else if pin == self.gnd {
+            PinRole::Gnd
+        } else if let Some(&(_, terms)) = config.outputs.iter().find(|&&(out, _)| out == pin) {
+            PinRole::Output(terms)
+        } else if let Some(&(_, column)) = config.special_pins.iter().find(|&&(sp, _)| sp == pin) {
+            PinRole::Special(column)
+        } else if config.clock == Some(pin) {
+            PinRole::Clock
+        } else if config.inputs.contains(&pin) {
+            PinRole::Input
+        } else {
+            PinRole::Unused
+        }
+    }
+}
+
+/// How much of a device a design uses as (used, available). `error` says why it doesn't fit,
+/// the counts are filled in as far as the design got.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Utilization {
+    pub device: &'static str,
+    pub mode: Option<&'static str>,
+    pub pins: (usize, usize),
+    pub olmcs: (usize, usize),
+    pub terms: (usize, usize),
+    pub error: Option<String>,
+}
+
+fn device_file(json: &str) -> CircuitConfig {
+    CircuitConfig::from_json(json).expect("the device files in Configs are valid")
+}
+
+fn dip(num_pins: u32) -> Package {
+    Package {
+        name: match num_pins {
+            20 => "DIP20",
+            24 => "DIP24",
+            28 => "DIP28",
+            _ => unreachable!("there is no device with {} pins", num_pins),
+        },
+        pins: (1..=num_pins).collect(),
+    }
+}
+
+// every side of the PLCC starts with an unconnected pin
+fn plcc28() -> Package {
+    Package {
+        name: "PLCC28",
+        pins: (1..=24).map(|pin| pin + 1 + (pin - 1) / 6).collect(),
+    }
+}
+
+impl OGal {
+    /// fits the design onto the device, signals without `pin` are placed by `OGal::fit`
+    pub fn utilization(&self, device: &Device) -> Utilization {
+        let config = &device.config;
+        // an OLMC can have more terms if it is registered (see `Mode::terms`)
+        let terms = config.outputs.iter().map(|&(pin, max)| {
+            let registered = config.terms(pin, true).unwrap_or(max);
+            max.max(registered) as usize
+        });
+        let mut utilization = Utilization {
+            device: device.name,
+            mode: device.mode,
+            pins: (0, config.num_pins as usize),
+            olmcs: (0, config.outputs.len()),
+            terms: (0, terms.sum()),
+            error: None,
+        };
+        if let Err(msg) = measure(self.clone(), config, &mut utilization) {
+            utilization.error = Some(msg);
+        }
+        utilization
+    }
+
+    /// The device named by the `device` statement in the mode the design needs (see
+    /// `select_mode`) or else the smallest known device the design fits into. Every mode of a
+    /// device is tried, a design with registers or `.oe` only fits into the modes which have them.
+    /// Returns the utilization of every device it was tried on as well.
+    pub fn select_device(&self) -> Result<(Device, Vec<Utilization>), String> {
+        if let Some(name) = &self.header().device {
+            return match Device::by_name(name) {
+                Some(device) => self.select_mode(&device),
+                None => Err(format!("unknown device {}", name)),
+            };
+        }
+        let mut tried = Vec::new();
+        for device in Device::all() {
+            let utilization = self.utilization(device);
+            let fits = utilization.error.is_none();
+            tried.push(utilization);
+            if fits {
+                return Ok((device.clone(), tried));
+            }
+        }
+        Err(format!(
+            "the design doesn't fit into any device\n{}",
+            utilization_table(&tried)
+        ))
+    }
+
+    /// The mode of `device` the design fits into, a device without modes is only checked. `.dff`
+    /// needs a mode with registers and `.oe` one with OE terms, without either the modes are tried
+    /// in order. Returns the utilization of every mode it was tried on as well.
+    pub fn select_mode(&self, device: &Device) -> Result<(Device, Vec<Utilization>), String> {
+        let modes = Device::modes(device.name);
+        let mut candidates: Vec<&Device> = modes.iter().collect();
+        let mut needs = Vec::new();
+
+        // a device without modes has the same OLMCs for every design, the fitter explains it
+        if device.mode.is_some() {
+            if let Some(name) = self.dff.first() {
+                candidates.retain(|mode| has(mode, |olmc| olmc.registered.is_some()));
+                needs.push(format!(
+                    "{}.dff needs the {} mode",
+                    name,
+                    mode_names(&candidates)
+                ));
+            }
+            // the registers of a mode may be enabled by a pin instead of their OE term
+            let oe = self.controls.iter().find(|(_, c, _)| *c == Control::Oe);
+            if let Some((name, _, _)) = oe {
+                let registered = self.dff.contains(name);
+                let has_oe = |mode: &Device| {
+                    has(mode, |olmc| match &olmc.registered {
+                        Some(mode) if registered => olmc.oe_row.is_some() && !mode.external_oe,
+                        _ => olmc.oe_row.is_some(),
+                    })
+                };
+                let with_oe: Vec<&Device> = modes.iter().filter(|mode| has_oe(mode)).collect();
+                if with_oe.is_empty() {
+                    return Err(format!(
+                        "{}.oe needs a mode of the {} with OE terms",
+                        name, device.name
+                    ));
+                }
+                candidates.retain(|mode| has_oe(mode));
+                needs.push(format!(
+                    "{}.oe needs the {} mode",
+                    name,
+                    mode_names(&with_oe)
+                ));
+            }
+            if candidates.is_empty() {
+                return Err(format!("{} of the {}", needs.join(" but "), device.name));
+            }
+        }
+
+        let mut tried = Vec::new();
+        for mode in candidates {
+            let utilization = self.utilization(mode);
+            let fits = utilization.error.is_none();
+            tried.push(utilization);
+            if fits {
+                return Ok((mode.clone(), tried));
+            }
+        }
+        match (needs.is_empty(), &tried[0].error) {
+            (false, Some(msg)) => Err(format!(
+                "{} of the {} which the design doesn't fit into: {}",
+                needs.join(" and "),
+                device.name,
+                msg
+            )),
+            _ => Err(format!(
+                "the design doesn't fit into the {}\n{}",
+                device.name,
+                utilization_table(&tried)
+            )),
+        }
+    }
+
+    /// Compiles the design for `device` in the mode it needs (see `select_mode`) with the header
+    /// and the tests of the source.
+    pub fn to_jedec(&self, device: &Device) -> Result<String, String> {
+        let (device, _) = self.select_mode(device)?;
+        let mut o_gal = self.clone();
+        o_gal.fit(&device.config)?;
+        o_gal.verify()?;
+        to_jedec_with_vectors(
+            &o_gal.table_data()?,
+            &device.config,
+            Some(self.header().jedec()),
+            &o_gal.jedec_vectors(&device.config)?,
+        )
+    }
+}
+
+/// if one of the OLMCs of the device can be used like this
+fn has(device: &Device, f: impl Fn(&Olmc) -> bool) -> bool {
+    device.config.architecture().olmcs.iter().any(f)
+}
+
+/// e.g. `complex or registered`
+fn mode_names(modes: &[&Device]) -> String {
+    let names: Vec<&str> = modes.iter().filter_map(|mode| mode.mode).collect();
+    names.join(" or ")
+}
+
+fn measure(
+    mut o_gal: OGal,
+    config: &CircuitConfig,
+    utilization: &mut Utilization,
+) -> Result<(), String> {
+    o_gal.fit(config)?;
+    let mut pins: Vec<&usize> = o_gal.pins().values().collect();
+    pins.retain(|&&pin| pin <= config.num_pins as usize);
+    pins.sort_unstable();
+    pins.dedup();
+    utilization.pins.0 = pins.len();
+
+    let tables = o_gal.table_data()?;
+    let mut exprs = Vec::new();
+    for table in tables.iter() {
+        exprs.push(Expression::new(table, config)?);
+    }
+    let (exprs, _) = split(exprs, &tables, config)?;
+    utilization.olmcs.0 = exprs.len();
+    utilization.terms.0 = exprs.iter().map(|expr| expr.rows.len()).sum();
+
+    to_jedec(&tables, config, None)?;
+    Ok(())
+}
+
+/// one line per device e.g.
+///
+/// ```text
+/// device              pins   olmcs  terms
+/// GAL16V8 simple      6/20   3/8    12/64   fits
+/// ```
+pub fn utilization_table(utilizations: &[Utilization]) -> String {
+    let mut table = "device              pins   olmcs  terms\n".to_string();
+    for utilization in utilizations {
+        let cell = |(used, available): (usize, usize)| format!("{}/{}", used, available);
+        let line = format!(
+            "{:<20}{:<7}{:<7}{:<9}{}",
+            match utilization.mode {
+                Some(mode) => format!("{} {}", utilization.device, mode),
+                None => utilization.device.to_string(),
+            },
+            cell(utilization.pins),
+            cell(utilization.olmcs),
+            cell(utilization.terms),
+            match &utilization.error {
+                None => "fits".to_string(),
+                Some(msg) => format!("doesn't fit: {}", msg),
+            }
+        );
+        table.push_str(&line);
+        table.push('\n');
+    }
+    table
+}
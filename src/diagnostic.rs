@@ -0,0 +1,116 @@
+use crate::ast::{position, Span, SyntaxError};
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// An error or warning about the source, rendered the same way by the command line and the
+/// language server.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// the name of the lint which produced it
+    pub code: Option<String>,
+    pub msg: String,
+    /// `None` if the error can't be pinned to a location in the source
+    pub span: Option<Span>,
+    /// other locations which belong to the diagnostic e.g. the first of two drivers
+    #[serde(default)]
+    pub related: Vec<(Span, String)>,
+}
+
+impl Diagnostic {
+    pub fn error(msg: &str, span: Option<Span>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: None,
+            msg: msg.to_string(),
+            span,
+            related: Vec::new(),
+        }
+    }
+
+    /// formats the diagnostic with the line it points to e.g.
+    ///
+    /// ```text
+    /// warning[unused_pin]: pin c is never used
+    ///  --> adder.ogal:3:9
+    ///   |
+    /// 3 | pin 3 = c;
+    ///   |         ^
+    /// ```
+    pub fn render(&self, code: &str, file: &str) -> String {
+        let mut result = match self.severity {
+            Severity::Error => "error".to_string(),
+            Severity::Warning => "warning".to_string(),
+        };
+        if let Some(name) = &self.code {
+            result.push_str(&format!("[{}]", name));
+        }
+        result.push_str(&format!(": {}\n", self.msg));
+
+        match self.span {
+            Some(span) => result.push_str(&snippet(code, file, span)),
+            None => result.push_str(&format!(" --> {}\n", file)),
+        }
+        for (span, msg) in self.related.iter() {
+            result.push_str(&format!("note: {}\n", msg));
+            result.push_str(&snippet(code, file, *span));
+        }
+        result
+    }
+}
+
+/// the location and the marked line of a span
+fn snippet(code: &str, file: &str, span: Span) -> String {
+    let (line, column) = position(code, span.start);
+    let text = code.lines().nth(line).unwrap_or("");
+    let len = if position(code, span.end).0 == line {
+        code[span.start..span.end].chars().count().max(1)
+    } else {
+        text.chars().count().saturating_sub(column).max(1)
+    };
+    let number = (line + 1).to_string();
+    let gutter = " ".repeat(number.len());
+
+    let mut result = format!("{}--> {}:{}:{}\n", gutter, file, line + 1, column + 1);
+    result.push_str(&format!("{} |\n", gutter));
+    result.push_str(&format!("{} | {}\n", number, text));
+    result.push_str(&format!(
+        "{} | {}{}\n",
+        gutter,
+        " ".repeat(column),
+        "^".repeat(len)
+    ));
+    result
+}
+
+impl From<SyntaxError> for Diagnostic {
+    fn from(err: SyntaxError) -> Self {
+        Self::error(&err.msg, Some(err.span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, Severity};
+    use crate::ast::Span;
+
+    #[test]
+    fn render() {
+        let diagnostic = Diagnostic {
+            severity: Severity::Warning,
+            code: Some("unused_pin".to_string()),
+            msg: "pin c is never used".to_string(),
+            span: Some(Span::new(19, 20)),
+            related: Vec::new(),
+        };
+        assert_eq!(
+            diagnostic.render("pin 1 = a;\npin 3 = c;\n", "adder.ogal"),
+            "warning[unused_pin]: pin c is never used\n --> adder.ogal:2:9\n  |\n2 | pin 3 = c;\n  |         ^\n"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::translator::jedec::OPENGAL_VERSION;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Design metadata taken from the `name`, `partno`, `revision`, `designer`, `company`, `date` and
+/// `device` statements of a source file.
+///
+/// Nothing in here depends on the time of the build unless `timestamp` is called, so the same
+/// source always produces the same JEDEC and WinCUPL output.
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Header {
+    pub name: Option<String>,
+    pub partno: Option<String>,
+    pub revision: Option<String>,
+    pub designer: Option<String>,
+    pub company: Option<String>,
+    pub date: Option<String>,
+    /// the part the design is for, without it the smallest one it fits into is picked
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+impl Header {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// fills in todays date (dd/mm/yyyy) if the source didn't set one
+    pub fn timestamp(mut self) -> Self {
+        if self.date.is_none() {
+            self.date = Some(today());
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.device.is_none() && self.fields().iter().all(|(_, val)| val.is_none())
+    }
+
+    /// the design specification and `N` note fields which go between STX and the first field of a JEDEC file
+    pub fn jedec(&self) -> String {
+        let mut result = format!("Created by {}\n", OPENGAL_VERSION);
+        for (key, val) in self.fields() {
+            if let Some(val) = val {
+                result.push_str(&format!("*N {:<9}{}\n", key, val));
+            }
+        }
+        result
+    }
+
+    /// the WinCUPL header block, missing fields are written as `None` like the WinCUPL template does
+    pub fn wincupl(&self, device: &str) -> String {
+        let mut result = String::new();
+        for (key, val) in self.fields() {
+            result.push_str(&format!(
+                "{:<9}{} ;\n",
+                key,
+                val.clone().unwrap_or_else(|| "None".to_string())
+            ));
+        }
+        result.push_str(&format!("{:<9}{} ;\n", "Device", device));
+        result
+    }
+
+    fn fields(&self) -> Vec<(&str, &Option<String>)> {
+        vec![
+            ("Name", &self.name),
+            ("PartNo", &self.partno),
+            ("Date", &self.date),
+            ("Revision", &self.revision),
+            ("Designer", &self.designer),
+            ("Company", &self.company),
+        ]
+    }
+}
+
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!("{:02}/{:02}/{}", day, month, year)
+}
+
+/// converts days since 1970-01-01 into (year, month, day)
+/// see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn civil_from_days() {
+        assert_eq!(super::civil_from_days(0), (1970, 1, 1));
+        assert_eq!(super::civil_from_days(18928), (2021, 10, 28));
+    }
+
+    #[test]
+    fn jedec() {
+        let mut header = super::Header::new();
+        header.name = Some("counter".to_string());
+        header.revision = Some("02".to_string());
+
+        assert_eq!(
+            header.jedec(),
+            "Created by open-gal 0.1.0\n*N Name     counter\n*N Revision 02\n"
+        );
+        assert!(header.timestamp().date.is_some());
+    }
+}
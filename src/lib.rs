@@ -1,33 +1,52 @@
+mod architecture;
+mod assertion;
 mod circuit_config;
+mod device;
+mod diagnostic;
+mod header;
+pub mod lint;
+pub mod lsp;
 mod parser;
+mod simulator;
 mod table_data;
+mod test_vector;
 mod translator;
 mod transpiler;
 
+pub use architecture::{Architecture, Mode, Olmc};
+pub use assertion::{Assertion, Property};
 pub use circuit_config::CircuitConfig;
-pub use table_data::TableData;
-pub use translator::core::to_jedec;
+pub use device::{utilization_table, Device, Package, PinRole, Utilization};
+pub use diagnostic::{Diagnostic, Severity};
+pub use header::Header;
+pub use table_data::{Control, ControlTable, TableData};
+pub use test_vector::{Level, TestBlock, TestVector};
+pub use translator::core::{to_jedec, to_jedec_with_vectors};
 
-pub use parser::OGal;
+pub use parser::ast;
+pub use parser::{format_code, write_pins, Hazard, OGal, ParserOptions};
 pub use transpiler::wincupl::to_wincupl;
 
 pub fn parse(code: &str) -> Result<Vec<TableData>, String> {
-    match parser::parse(code) {
-        Err(error) => Err(format!("{:?}", error)),
-        Ok(td_vec) => Ok(td_vec),
-    }
+    parse_with_options(code, ParserOptions::default())
 }
 
-// when parsing pin the number comes first
-// e.g. if NUM_FIRST == true `pin 1 = a;` else `pin a = 1;`
-pub const NUM_FIRST: bool = true;
-pub const COUNT_VERTICAL: bool = false;
+/// like `parse` but with a different dialect e.g. `pin a = 1;` (see `ParserOptions`)
+pub fn parse_with_options(code: &str, options: ParserOptions) -> Result<Vec<TableData>, String> {
+    parse_with_header(code, options).map(|(td_vec, _)| td_vec)
+}
 
-// symbols for logical operators
-// pub const AND: &str = "&";
-// pub const OR: &str = "|";
-// pub const XOR: &str = "?";
-// pub const NOT: &str = "!";
+/// like `parse_with_options` but also returns the `name`, `date`, ... statements, pass
+/// `header.jedec()` as `head` to `to_jedec` and `header.wincupl(device)` to `to_wincupl`
+pub fn parse_with_header(
+    code: &str,
+    options: ParserOptions,
+) -> Result<(Vec<TableData>, Header), String> {
+    match parser::parse(code, options) {
+        Err(error) => Err(format!("{:?}", error)),
+        Ok(parsed) => Ok(parsed),
+    }
+}
 
 //	Version string for JEDEC file
 // pub const OPENGAL_VERSION: &str = "open-gal 0.1.0"; is uesd in translator/jedec.rs
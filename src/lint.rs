@@ -0,0 +1,337 @@
+//! Warnings about code which compiles but is most likely a mistake.
+//!
+//! Every lint has a name and warns by default. `#pragma allow(unused_pin)` silences it and
+//! `#pragma deny(unused_pin, constant_output)` turns it into an error which fails the build.
+//! The level applies to the whole file regardless of where the pragma is.
+
+use crate::ast::{Ast, Name, Span, Statement, TableMode, Visitor};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::{OGal, ParserOptions};
+use hardware_sim::Component;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Lint {
+    /// a declared pin that is never used
+    UnusedPin,
+    /// `.dff` on a name that never gets an equation
+    DffWithoutEquation,
+    /// the same inputs twice in a `.fill` table
+    DuplicateRows,
+    /// an output which depends on inputs but is always 0 or always 1
+    ConstantOutput,
+    /// feedback between combinational outputs which isn't declared with `.latch`
+    CombinationalLoop,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 5] = [
+        Lint::UnusedPin,
+        Lint::DffWithoutEquation,
+        Lint::DuplicateRows,
+        Lint::ConstantOutput,
+        Lint::CombinationalLoop,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedPin => "unused_pin",
+            Lint::DffWithoutEquation => "dff_without_equation",
+            Lint::DuplicateRows => "duplicate_rows",
+            Lint::ConstantOutput => "constant_output",
+            Lint::CombinationalLoop => "combinational_loop",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Lint::ALL.iter().find(|lint| lint.name() == name).cloned()
+    }
+
+    pub fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+}
+
+/// parses the text after `#pragma` if it is `allow(...)`, `warn(...)` or `deny(...)`
+pub fn pragma(text: &str) -> Option<Result<(LintLevel, Vec<Lint>), String>> {
+    let text = text.trim();
+    let (level, rest) = if let Some(rest) = text.strip_prefix("allow") {
+        (LintLevel::Allow, rest)
+    } else if let Some(rest) = text.strip_prefix("warn") {
+        (LintLevel::Warn, rest)
+    } else if let Some(rest) = text.strip_prefix("deny") {
+        (LintLevel::Deny, rest)
+    } else {
+        return None;
+    };
+
+    let rest = rest.trim();
+    if !rest.starts_with('(') || !rest.ends_with(')') {
+        return Some(Err(format!(
+            "expected e.g. `#pragma allow(unused_pin)` but got {}",
+            text
+        )));
+    }
+
+    let mut lints = Vec::new();
+    for name in rest[1..rest.len() - 1].split(',') {
+        match Lint::from_name(name.trim()) {
+            Some(lint) => lints.push(lint),
+            None => {
+                let names: Vec<&str> = Lint::ALL.iter().map(|lint| lint.name()).collect();
+                return Some(Err(format!(
+                    "unknown lint {} expected one of {}",
+                    name.trim(),
+                    names.join(", ")
+                )));
+            }
+        }
+    }
+    Some(Ok((level, lints)))
+}
+
+/// Parses the code and runs all lints. Errors which stop the parser are returned as `Err`, denied
+/// lints come back as diagnostics with `Severity::Error`.
+pub fn check(code: &str, options: ParserOptions) -> Result<Vec<Diagnostic>, Diagnostic> {
+    let ast = Ast::parse_with(code, options.clone())?;
+    let o_gal = OGal::from_ast(&ast, options)?;
+    Ok(lints(&ast, &o_gal))
+}
+
+/// runs all lints on a design which is already parsed, `o_gal` has to be lowered from `ast`
+pub(crate) fn lints(ast: &Ast, o_gal: &OGal) -> Vec<Diagnostic> {
+    let mut levels = HashMap::new();
+    for statement in ast.statements.iter() {
+        if let Statement::Pragma(pragma) = statement {
+            if let Some(Ok((level, lints))) = self::pragma(&pragma.text) {
+                for lint in lints {
+                    levels.insert(lint, level);
+                }
+            }
+        }
+    }
+
+    let mut linter = Linter {
+        levels,
+        diagnostics: Vec::new(),
+    };
+    linter.names(ast);
+    linter.registers(ast);
+    linter.duplicate_rows(ast);
+    linter.constant_output(ast, o_gal);
+    linter.loops(ast, o_gal);
+    linter
+        .diagnostics
+        .sort_by_key(|diagnostic| diagnostic.span.map(|span| span.start));
+    linter.diagnostics
+}
+
+struct Linter {
+    levels: HashMap<Lint, LintLevel>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn warn(&mut self, lint: Lint, msg: String, span: Span) {
+        let severity = match self
+            .levels
+            .get(&lint)
+            .cloned()
+            .unwrap_or(lint.default_level())
+        {
+            LintLevel::Allow => return,
+            LintLevel::Warn => Severity::Warning,
+            LintLevel::Deny => Severity::Error,
+        };
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code: Some(lint.name().to_string()),
+            msg,
+            span: Some(span),
+            related: Vec::new(),
+        });
+    }
+
+    fn names(&mut self, ast: &Ast) {
+        struct Uses(Vec<String>);
+        impl Visitor for Uses {
+            fn visit_pin(&mut self, _pin: &crate::ast::PinDecl) {}
+            fn visit_name(&mut self, name: &Name) {
+                self.0.push(name.name.clone());
+            }
+        }
+
+        let mut uses = Uses(Vec::new());
+        ast.accept(&mut uses);
+
+        for statement in ast.statements.iter() {
+            if let Statement::Pin(pin) = statement {
+                for name in pin.names.iter() {
+                    if !uses.0.contains(&name.name) {
+                        self.warn(
+                            Lint::UnusedPin,
+                            format!("pin {} is never used", name.name),
+                            name.span,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn registers(&mut self, ast: &Ast) {
+        let driven: Vec<&str> = driven_names(ast)
+            .iter()
+            .map(|name| name.name.as_str())
+            .collect();
+
+        for statement in ast.statements.iter() {
+            let names = match statement {
+                Statement::Dff(dff) => &dff.names,
+                Statement::Node(node) if node.dff => &node.names,
+                _ => continue,
+            };
+            for name in names.iter() {
+                if !driven.contains(&name.name.as_str()) {
+                    self.warn(
+                        Lint::DffWithoutEquation,
+                        format!("{} is a register but never gets an equation", name.name),
+                        name.span,
+                    );
+                }
+            }
+        }
+    }
+
+    fn duplicate_rows(&mut self, ast: &Ast) {
+        for statement in ast.statements.iter() {
+            let table = match statement {
+                Statement::Table(table) if matches!(table.mode, TableMode::Fill(_)) => table,
+                _ => continue,
+            };
+
+            // the bits of a row may be split over several numbers, so remember where each starts
+            let mut bits = Vec::new();
+            for number in table.rows.iter() {
+                for c in number.bits.chars() {
+                    bits.push((c, number.span));
+                }
+            }
+
+            let row_len = table.inputs.len() + table.outputs.len();
+            let mut seen: HashMap<String, usize> = HashMap::new();
+            for (i, row) in bits.chunks(row_len).enumerate() {
+                let inputs: String = row
+                    .iter()
+                    .take(table.inputs.len())
+                    .map(|&(c, _)| c)
+                    .collect();
+                if let Some(first) = seen.insert(inputs.clone(), i) {
+                    self.warn(
+                        Lint::DuplicateRows,
+                        format!(
+                            "row {} has the same inputs {} as row {}",
+                            i + 1,
+                            inputs,
+                            first + 1
+                        ),
+                        row[0].1,
+                    );
+                }
+            }
+        }
+    }
+
+    fn constant_output(&mut self, ast: &Ast, o_gal: &OGal) {
+        for name in driven_names(ast) {
+            // `|=` merges into the table of the first statement, so look it up by its output
+            let lut = o_gal.luts().iter().find_map(|lut| {
+                let index = lut.out_names().iter().position(|out| *out == name.name)?;
+                Some((lut, index))
+            });
+            if let Some((lut, index)) = lut {
+                if lut.in_names().is_empty() {
+                    continue;
+                }
+                let table = &lut.get_table()[index];
+                if table.iter().all(|&val| val == table[0]) {
+                    self.warn(
+                        Lint::ConstantOutput,
+                        format!("{} is always {}", name.name, table[0] as u8),
+                        name.span,
+                    );
+                }
+            }
+        }
+    }
+
+    fn loops(&mut self, ast: &Ast, o_gal: &OGal) {
+        let driven = driven_names(ast);
+        for path in o_gal.combinational_loops() {
+            if path.iter().any(|name| o_gal.latches().contains(name)) {
+                continue;
+            }
+            let span = match driven.iter().find(|name| name.name == path[0]) {
+                Some(name) => name.span,
+                None => continue,
+            };
+
+            let names: Vec<String> = path
+                .iter()
+                .map(|name| match o_gal.pins().get(name) {
+                    Some(pin) => format!("{} (pin {})", name, pin),
+                    None => name.clone(),
+                })
+                .collect();
+            self.warn(
+                Lint::CombinationalLoop,
+                format!(
+                    "combinational loop {}, declare `{}.latch;` if it is intended",
+                    names.join(" -> "),
+                    path[0]
+                ),
+                span,
+            );
+        }
+    }
+}
+
+/// the outputs of every assignment, `table` and `rom` in source order
+fn driven_names(ast: &Ast) -> Vec<&Name> {
+    let mut result = Vec::new();
+    for statement in ast.statements.iter() {
+        match statement {
+            Statement::Assignment(s) if !s.merge => result.extend(s.targets.iter()),
+            Statement::Table(s) => result.extend(s.outputs.iter()),
+            Statement::Rom(s) => result.extend(s.outputs.iter()),
+            _ => (),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pragma, Lint, LintLevel};
+
+    #[test]
+    fn pragmas() {
+        assert_eq!(
+            pragma(" deny(unused_pin, constant_output)"),
+            Some(Ok((
+                LintLevel::Deny,
+                vec![Lint::UnusedPin, Lint::ConstantOutput]
+            )))
+        );
+        assert_eq!(pragma("name_first"), None);
+        assert!(matches!(pragma("allow(unknown)"), Some(Err(_))));
+    }
+}
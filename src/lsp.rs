@@ -0,0 +1,599 @@
+//! A language server for `.ogal` files which speaks JSON-RPC over stdio.
+//!
+//! Documents are synced in full on every change. Columns are counted in chars, which is what
+//! editors send for the ascii source the language consists of.
+
+use crate::ast::{self, Ast, Name, Span, Statement, Visitor};
+use crate::parser::Token;
+use crate::translator::dnf::Expression;
+use crate::{lint, CircuitConfig, Diagnostic, OGal, ParserOptions, Severity};
+use hardware_sim::Component;
+use logos::Logos;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+const KEYWORDS: [&str; 27] = [
+    "pin",
+    "node",
+    "table",
+    "fill",
+    "count",
+    "dff",
+    "latch",
+    "hazard_free",
+    "helper",
+    "oe",
+    "clk",
+    "ar",
+    "ap",
+    "rom",
+    "from",
+    "test",
+    "assert",
+    "always",
+    "eventually",
+    "within",
+    "name",
+    "partno",
+    "revision",
+    "designer",
+    "company",
+    "date",
+    "device",
+];
+
+/// the legend of the semantic tokens, the index is the token type sent to the editor
+const TOKEN_TYPES: [&str; 6] = [
+    "keyword", "variable", "number", "comment", "string", "operator",
+];
+
+/// serves requests from `input` until the client sends `exit` or closes the stream
+pub fn run(input: &mut impl BufRead, output: &mut impl Write) -> Result<(), String> {
+    let mut server = Server::new();
+    while let Some(msg) = read_message(input)? {
+        for reply in server.handle(&msg) {
+            write_message(output, &reply)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// the largest message the server reads, the buffer for it is allocated up front
+const MAX_MESSAGE_LEN: usize = 4 << 20;
+
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>, String> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            Ok(0) => return Ok(None),
+            Ok(_) => (),
+            Err(err) => return Err(format!("can't read message: {}", err)),
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() && len.is_some() {
+            break;
+        }
+        if let Some(val) = line.strip_prefix("Content-Length:") {
+            len = match val.trim().parse::<usize>() {
+                Ok(val) if val > MAX_MESSAGE_LEN => {
+                    return Err(format!(
+                        "message of {} bytes is larger than {} bytes",
+                        val, MAX_MESSAGE_LEN
+                    ))
+                }
+                Ok(val) => Some(val),
+                Err(_) => return Err(format!("invalid Content-Length {:?}", val.trim())),
+            };
+        }
+    }
+
+    let mut buf = vec![0; len.unwrap()];
+    if let Err(err) = input.read_exact(&mut buf) {
+        return Err(format!("can't read message: {}", err));
+    }
+    match serde_json::from_slice(&buf) {
+        Ok(msg) => Ok(Some(msg)),
+        Err(err) => Err(format!("invalid message: {}", err)),
+    }
+}
+
+fn write_message(output: &mut impl Write, msg: &Value) -> Result<(), String> {
+    let body = msg.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| output.flush())
+        .map_err(|err| format!("can't write message: {}", err))
+}
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, String>,
+    options: ParserOptions,
+    /// the device hovers show the role of a pin on, otherwise the one of the file is used
+    device: Option<CircuitConfig>,
+    exit: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_device(device: CircuitConfig) -> Self {
+        Self {
+            device: Some(device),
+            ..Self::default()
+        }
+    }
+
+    pub fn exited(&self) -> bool {
+        self.exit
+    }
+
+    /// handles one request or notification and returns the messages to send back
+    pub fn handle(&mut self, msg: &Value) -> Vec<Value> {
+        let method = msg["method"].as_str().unwrap_or("");
+        let params = &msg["params"];
+        let id = msg["id"].clone();
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "renameProvider": true,
+                    "completionProvider": {},
+                    "semanticTokensProvider": {
+                        "legend": { "tokenTypes": TOKEN_TYPES, "tokenModifiers": [] },
+                        "full": true
+                    }
+                },
+                "serverInfo": { "name": "open-gal" }
+            })),
+            "shutdown" => Ok(Value::Null),
+            "exit" => {
+                self.exit = true;
+                return Vec::new();
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.documents.insert(uri.clone(), text.to_string());
+                return vec![self.diagnostics(&uri)];
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes
+                    .and_then(|c| c.last())
+                    .and_then(|c| c["text"].as_str())
+                {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                return vec![self.diagnostics(&uri)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![notification(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )];
+            }
+            "textDocument/hover" => Ok(self.hover(&uri, params)),
+            "textDocument/definition" => Ok(self.definition(&uri, params)),
+            "textDocument/rename" => self.rename(&uri, params),
+            "textDocument/completion" => Ok(self.completion(&uri)),
+            "textDocument/semanticTokens/full" => Ok(self.semantic_tokens(&uri)),
+            _ if id.is_null() || method.is_empty() => return Vec::new(),
+            _ => Err((-32601, format!("method {} not found", method))),
+        };
+
+        if id.is_null() {
+            return Vec::new();
+        }
+        match result {
+            Ok(result) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            Err((code, msg)) => vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": msg }
+            })],
+        }
+    }
+
+    fn code(&self, uri: &str) -> &str {
+        self.documents.get(uri).map_or("", |code| code.as_str())
+    }
+
+    /// `rom` files are looked up next to the document
+    fn options(&self, uri: &str) -> ParserOptions {
+        let mut options = self.options.clone();
+        if let Some(path) = uri.strip_prefix("file://") {
+            if let Some(dir) = std::path::Path::new(path).parent() {
+                options.rom_dir = Some(dir.to_string_lossy().to_string());
+            }
+        }
+        options
+    }
+
+    fn diagnostics(&self, uri: &str) -> Value {
+        let code = self.code(uri);
+        let mut diagnostics = Vec::new();
+
+        let parsed = match Ast::parse_with(code, self.options(uri)) {
+            Ok(ast) => OGal::from_ast(&ast, self.options(uri)).map(|o_gal| (ast, o_gal)),
+            Err(err) => Err(err.into()),
+        };
+        match parsed {
+            Ok((ast, o_gal)) => {
+                let lints = lint::lints(&ast, &o_gal);
+                for lint in lints.iter() {
+                    diagnostics.push(diagnostic(uri, code, lint));
+                }
+                if !lints.iter().any(|lint| lint.severity == Severity::Error) {
+                    if let Err(msg) = o_gal.verify().and_then(|_| o_gal.table_data()) {
+                        diagnostics.push(diagnostic(uri, code, &Diagnostic::error(&msg, None)));
+                    }
+                }
+            }
+            Err(err) => diagnostics.push(diagnostic(uri, code, &err)),
+        }
+
+        notification(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    fn hover(&self, uri: &str, params: &Value) -> Value {
+        let code = self.code(uri);
+        let ast = match Ast::parse_with(code, self.options(uri)) {
+            Ok(ast) => ast,
+            Err(_) => return Value::Null,
+        };
+        let name = match name_at(&ast, offset(code, &params["position"])) {
+            Some(name) => name,
+            None => return Value::Null,
+        };
+
+        let pin = pins(&ast).get(&name.name).cloned();
+        let mut lines = vec![match pin {
+            Some(pin) => format!("`{}` pin {}", name.name, pin),
+            None => format!("`{}` internal signal", name.name),
+        }];
+
+        let o_gal = OGal::from_ast(&ast, self.options(uri)).ok();
+        // without a device of the server the one `open-gal fit` picks, which is the one of the
+        // `device = "..."` statement in the mode the design needs
+        let device = match (&self.device, &o_gal) {
+            (Some(device), _) => Some(device.clone()),
+            (None, Some(o_gal)) => o_gal.select_device().ok().map(|(device, _)| device.config),
+            (None, None) => None,
+        };
+
+        let driven = o_gal.as_ref().is_some_and(|o_gal| {
+            o_gal
+                .luts()
+                .iter()
+                .any(|lut| lut.out_names().contains(&name.name))
+        });
+        let kind = match &o_gal {
+            Some(o_gal) if o_gal.dffs().contains(&name.name) => "registered",
+            _ => "combinational",
+        };
+        let terms = match (&o_gal, &device) {
+            (Some(o_gal), Some(device)) if driven => product_terms(o_gal, device, &name.name),
+            _ => None,
+        };
+        lines.push(match terms {
+            Some(terms) => format!("{} output using {} product terms", kind, terms),
+            None if driven => format!("{} output", kind),
+            None => "input".to_string(),
+        });
+
+        if let (Some(device), Some(pin)) = (&device, pin) {
+            let pin = pin as u32;
+            lines.push(
+                if let Some(&(_, terms)) = device.outputs.iter().find(|&&(out, _)| out == pin) {
+                    format!("device: output cell with {} product terms", terms)
+                } else if device
+                    .special_pins
+                    .iter()
+                    .any(|&(special, _)| special == pin)
+                {
+                    "device: special pin".to_string()
+                } else if device.inputs.contains(&pin) {
+                    "device: input".to_string()
+                } else {
+                    "device: not connected".to_string()
+                },
+            );
+        }
+
+        json!({
+            "contents": { "kind": "markdown", "value": lines.join("\n\n") },
+            "range": range(code, name.span)
+        })
+    }
+
+    fn definition(&self, uri: &str, params: &Value) -> Value {
+        let code = self.code(uri);
+        let ast = match Ast::parse_with(code, self.options(uri)) {
+            Ok(ast) => ast,
+            Err(_) => return Value::Null,
+        };
+        let name = match name_at(&ast, offset(code, &params["position"])) {
+            Some(name) => name,
+            None => return Value::Null,
+        };
+
+        match declaration(&ast, &name.name) {
+            Some(def) => json!({ "uri": uri, "range": range(code, def.span) }),
+            None => Value::Null,
+        }
+    }
+
+    fn rename(&self, uri: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let code = self.code(uri);
+        let ast = match Ast::parse_with(code, self.options(uri)) {
+            Ok(ast) => ast,
+            Err(err) => return Err((-32803, err.msg)),
+        };
+        let name = match name_at(&ast, offset(code, &params["position"])) {
+            Some(name) => name,
+            None => return Ok(Value::Null),
+        };
+        let new_name = params["newName"].as_str().unwrap_or("");
+        if !is_identifier(new_name) {
+            return Err((
+                -32602,
+                format!("{:?} isn't a valid name for a signal", new_name),
+            ));
+        }
+        // the other signal is reported where its pin is declared or else where it is first used
+        let other = declaration(&ast, new_name)
+            .or_else(|| names(&ast).into_iter().find(|other| other.name == new_name));
+        if let Some(other) = other.filter(|_| new_name != name.name) {
+            let (line, column) = ast::position(code, other.span.start);
+            return Err((
+                -32803,
+                format!(
+                    "{} is already declared at {}:{}, renaming {} would merge the two signals",
+                    new_name,
+                    line + 1,
+                    column + 1,
+                    name.name
+                ),
+            ));
+        }
+
+        let mut edits = Vec::new();
+        for other in names(&ast)
+            .into_iter()
+            .filter(|other| other.name == name.name)
+        {
+            if code[other.span.start..other.span.end] != other.name {
+                return Err((
+                    -32803,
+                    format!(
+                        "{} is part of a range and can't be renamed alone",
+                        name.name
+                    ),
+                ));
+            }
+            edits.push(json!({ "range": range(code, other.span), "newText": new_name }));
+        }
+
+        Ok(json!({ "changes": { uri: edits } }))
+    }
+
+    fn completion(&self, uri: &str) -> Value {
+        let mut items = Vec::new();
+        if let Ok(ast) = Ast::parse_with(self.code(uri), self.options(uri)) {
+            let mut pins: Vec<(String, usize)> = pins(&ast).into_iter().collect();
+            pins.sort_by_key(|&(_, num)| num);
+            for (name, num) in pins {
+                items.push(json!({ "label": name, "kind": 6, "detail": format!("pin {}", num) }));
+            }
+        }
+        for keyword in KEYWORDS.iter() {
+            items.push(json!({ "label": keyword, "kind": 14 }));
+        }
+        Value::Array(items)
+    }
+
+    fn semantic_tokens(&self, uri: &str) -> Value {
+        let code = self.code(uri);
+        let mut data = Vec::new();
+        let (mut prev_line, mut prev_column) = (0, 0);
+
+        // the header keys and contextual keywords are identifiers for the lexer, only the parser
+        // knows whether they name a signal
+        let names: Vec<usize> = match Ast::parse_with(code, self.options(uri)) {
+            Ok(ast) => names(&ast).iter().map(|name| name.span.start).collect(),
+            Err(_) => Vec::new(),
+        };
+        let keyword = |word: &str| {
+            ast::HEADER_KEYS.contains(&word) || ast::CONTEXTUAL_KEYWORDS.contains(&word)
+        };
+
+        for (token, span) in Token::lexer(code).spanned() {
+            let text = &code[span.clone()];
+            let token_type = match token {
+                Token::Identifier(word) if keyword(&word) && !names.contains(&span.start) => 0,
+                Token::Identifier(_) => 1,
+                Token::Number(_) | Token::Binary(_) => 2,
+                Token::Ignore(_) if text.starts_with("//") || text.starts_with("/*") => 3,
+                Token::Str(_) => 4,
+                Token::And
+                | Token::Or
+                | Token::Xor
+                | Token::CuplOr
+                | Token::CuplXor
+                | Token::Not
+                | Token::Equals
+                | Token::Arrow => 5,
+                Token::Ignore(_)
+                | Token::Unknown
+                | Token::Comma
+                | Token::Semicolon
+                | Token::Dot
+                | Token::CurlyOpen
+                | Token::RoundOpen
+                | Token::SquareOpen
+                | Token::CurlyClose
+                | Token::RoundClose
+                | Token::SquareClose => continue,
+                _ => 0,
+            };
+
+            // editors without multiline token support need one token per line
+            let mut start = span.start;
+            for part in text.trim_end().split('\n') {
+                let (line, column) = ast::position(code, start);
+                let delta_column = if line == prev_line {
+                    column - prev_column
+                } else {
+                    column
+                };
+                data.extend_from_slice(&[
+                    line - prev_line,
+                    delta_column,
+                    part.trim_end_matches('\r').chars().count(),
+                    token_type,
+                    0,
+                ]);
+                prev_line = line;
+                prev_column = column;
+                start += part.len() + 1;
+            }
+        }
+
+        json!({ "data": data })
+    }
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn diagnostic(uri: &str, code: &str, diagnostic: &Diagnostic) -> Value {
+    let related: Vec<Value> = diagnostic
+        .related
+        .iter()
+        .map(|(span, msg)| {
+            json!({ "location": { "uri": uri, "range": range(code, *span) }, "message": msg })
+        })
+        .collect();
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+    json!({
+        "range": range(code, diagnostic.span.unwrap_or_default()),
+        "severity": severity,
+        "code": diagnostic.code,
+        "source": "open-gal",
+        "message": diagnostic.msg,
+        "relatedInformation": related
+    })
+}
+
+fn range(code: &str, span: Span) -> Value {
+    let (start_line, start_column) = ast::position(code, span.start);
+    let (end_line, end_column) = ast::position(code, span.end);
+    json!({
+        "start": { "line": start_line, "character": start_column },
+        "end": { "line": end_line, "character": end_column }
+    })
+}
+
+/// the byte offset of an lsp position
+fn offset(code: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let column = position["character"].as_u64().unwrap_or(0) as usize;
+
+    let mut result = 0;
+    for (i, text) in code.split('\n').enumerate() {
+        if i == line {
+            return result
+                + text
+                    .char_indices()
+                    .nth(column)
+                    .map_or(text.len(), |(offset, _)| offset);
+        }
+        result += text.len() + 1;
+    }
+    code.len()
+}
+
+/// the product terms `name` is programmed with on the device, after the fitter placed the signals
+/// without `pin`
+fn product_terms(o_gal: &OGal, config: &CircuitConfig, name: &str) -> Option<usize> {
+    let mut o_gal = o_gal.clone();
+    o_gal.fit(config).ok()?;
+    let pin = *o_gal.pins().get(name)? as u32;
+    let tables = o_gal.table_data().ok()?;
+    let table = tables.iter().find(|table| table.output_pin == pin)?;
+    Expression::new(table, config)
+        .ok()
+        .map(|expr| expr.rows.len())
+}
+
+/// a name the lexer reads as one identifier, keywords and empty names aren't
+///
+/// the header keys are rejected too, `name = a;` would be parsed as a header field
+fn is_identifier(name: &str) -> bool {
+    let mut tokens = Token::lexer(name);
+    matches!(
+        (tokens.next(), tokens.next()),
+        (Some(Token::Identifier(ident)), None) if ident == name
+    ) && !ast::HEADER_KEYS.contains(&name)
+}
+
+/// the name in the `pin` statement of a signal
+fn declaration(ast: &Ast, name: &str) -> Option<Name> {
+    ast.statements.iter().find_map(|statement| match statement {
+        Statement::Pin(pin) => pin.names.iter().find(|def| def.name == name).cloned(),
+        _ => None,
+    })
+}
+
+fn names(ast: &Ast) -> Vec<Name> {
+    struct Names(Vec<Name>);
+    impl Visitor for Names {
+        fn visit_name(&mut self, name: &Name) {
+            self.0.push(name.clone());
+        }
+    }
+
+    let mut names = Names(Vec::new());
+    ast.accept(&mut names);
+    names.0
+}
+
+fn name_at(ast: &Ast, offset: usize) -> Option<Name> {
+    names(ast)
+        .into_iter()
+        .find(|name| name.span.start <= offset && offset <= name.span.end)
+}
+
+fn pins(ast: &Ast) -> HashMap<String, usize> {
+    let mut result = HashMap::new();
+    for statement in ast.statements.iter() {
+        if let Statement::Pin(pin) = statement {
+            for (name, &num) in pin.names.iter().zip(pin.numbers.iter()) {
+                result.insert(name.name.clone(), num);
+            }
+        }
+    }
+    result
+}
@@ -0,0 +1,173 @@
+use open_gal::{Diagnostic, OGal, Severity};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: open-gal check FILE...
+       open-gal fmt [--check] [FILE]...
+       open-gal fit [--write] FILE
+       open-gal lsp
+
+commands:
+    check  compiles the files and prints all errors and warnings
+    fmt    formats the files in place or stdin to stdout if no file is given,
+           with --check nothing is written and it fails if a file isn't formatted
+    fit    picks the smallest device the design fits into unless it has a `device` statement,
+           places the signals without a pin and prints the fit report, with --write the
+           pins are added to the file
+    lsp    runs the language server on stdin and stdout";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(|arg| arg.as_str()) {
+        Some("check") => check(&args[1..]),
+        Some("fmt") => fmt(&args[1..]),
+        Some("fit") => fit(&args[1..]),
+        Some("lsp") => open_gal::lsp::run(&mut io::stdin().lock(), &mut io::stdout()).map(|_| true),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(msg) => {
+            eprintln!("{}", msg);
+            process::exit(2);
+        }
+    }
+}
+
+/// returns false if a file has errors
+fn check(files: &[String]) -> Result<bool, String> {
+    if files.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let mut ok = true;
+    for file in files {
+        let code = match fs::read_to_string(file) {
+            Ok(code) => code,
+            Err(err) => return Err(format!("can't read {}: {}", file, err)),
+        };
+        let mut options = open_gal::ParserOptions::new();
+        if let Some(dir) = Path::new(file).parent() {
+            options.rom_dir = Some(dir.to_string_lossy().to_string());
+        }
+
+        let mut diagnostics = match open_gal::lint::check(&code, options.clone()) {
+            Ok(diagnostics) => diagnostics,
+            Err(diagnostic) => vec![diagnostic],
+        };
+        if !diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            if let Err(msg) = open_gal::parse_with_options(&code, options) {
+                diagnostics.push(Diagnostic::error(&msg, None));
+            }
+        }
+
+        for diagnostic in diagnostics.iter() {
+            eprintln!("{}", diagnostic.render(&code, file));
+            if diagnostic.severity == Severity::Error {
+                ok = false;
+            }
+        }
+    }
+
+    Ok(ok)
+}
+
+/// returns false if `--check` found a file which isn't formatted
+fn fmt(args: &[String]) -> Result<bool, String> {
+    let check = args.iter().any(|arg| arg == "--check");
+    let files: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+
+    if let Some(arg) = files.iter().find(|arg| arg.starts_with('-')) {
+        return Err(format!("unknown option {}\n\n{}", arg, USAGE));
+    }
+
+    if files.is_empty() {
+        let mut code = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut code) {
+            return Err(format!("can't read stdin: {}", err));
+        }
+        let formatted = open_gal::format_code(&code)?;
+        if check {
+            return Ok(formatted == code);
+        }
+        print!("{}", formatted);
+        return Ok(true);
+    }
+
+    let mut formatted_all = true;
+    for file in files {
+        let code = match fs::read_to_string(file) {
+            Ok(code) => code,
+            Err(err) => return Err(format!("can't read {}: {}", file, err)),
+        };
+        let formatted = match open_gal::format_code(&code) {
+            Ok(formatted) => formatted,
+            Err(msg) => return Err(format!("{}: {}", file, msg)),
+        };
+
+        if formatted == code {
+            continue;
+        }
+        if check {
+            println!("{} isn't formatted", file);
+            formatted_all = false;
+        } else if let Err(err) = fs::write(file, formatted) {
+            return Err(format!("can't write {}: {}", file, err));
+        }
+    }
+
+    Ok(formatted_all)
+}
+
+/// returns false if the design doesn't fit into any device
+fn fit(args: &[String]) -> Result<bool, String> {
+    let write = args.iter().any(|arg| arg == "--write");
+    let files: Vec<&String> = args.iter().filter(|arg| *arg != "--write").collect();
+    let file = match files.as_slice() {
+        [file] if !file.starts_with('-') => file,
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let code = match fs::read_to_string(file) {
+        Ok(code) => code,
+        Err(err) => return Err(format!("can't read {}: {}", file, err)),
+    };
+    let mut options = open_gal::ParserOptions::new();
+    if let Some(dir) = Path::new(file).parent() {
+        options.rom_dir = Some(dir.to_string_lossy().to_string());
+    }
+    let mut o_gal = match OGal::parse_with(&code, options.clone()) {
+        Ok(o_gal) => o_gal,
+        Err(err) => return Err(format!("{}: {:?}", file, err)),
+    };
+
+    let (device, tried) = match o_gal.select_device() {
+        Ok(selected) => selected,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return Ok(false);
+        }
+    };
+    print!("{}", open_gal::utilization_table(&tried));
+
+    let assigned = o_gal.fit(&device.config)?;
+    println!("\n{}", device.full_name());
+    print!("{}", o_gal.fit_report(&device.config)?);
+
+    if write && !assigned.is_empty() {
+        let code = open_gal::write_pins(&code, options, &assigned)?;
+        if let Err(err) = fs::write(file, code) {
+            return Err(format!("can't write {}: {}", file, err));
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,1187 @@
+//! A syntax tree of the source with the byte span of every statement and name.
+//!
+//! `OGal` only keeps what the design does, the `Ast` keeps how it was written including
+//! comments. `OGal::parse` builds the `Ast` and lowers it with `OGal::from_ast`, every
+//! `Assignment` without `|=`, `TableDecl` and `RomDecl` produces one lookup table.
+
+use super::{ParserOptions, Token};
+use crate::assertion::Property;
+use crate::Control;
+use logos::Logos;
+use serde::{Deserialize, Serialize};
+use tokenizer::TypeEq;
+
+/// byte offsets into the source, `end` is exclusive
+#[derive(PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// the smallest span containing both
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    /// zero based line and column (in chars) of the start
+    pub fn position(&self, code: &str) -> (usize, usize) {
+        position(code, self.start)
+    }
+}
+
+/// zero based line and column (in chars) of a byte offset
+pub fn position(code: &str, offset: usize) -> (usize, usize) {
+    let before = &code[..offset.min(code.len())];
+    let line = before.matches('\n').count();
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count();
+    (line, column)
+}
+
+/// A signal name, `a[0..2]` is expanded into `a0`, `a1` and `a2` which share the span.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Name {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Ast {
+    pub statements: Vec<Statement>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum Statement {
+    Pin(PinDecl),
+    Node(NodeDecl),
+    Assignment(Assignment),
+    Table(TableDecl),
+    Rom(RomDecl),
+    Dff(DffDecl),
+    Latch(LatchDecl),
+    HazardFree(HazardFreeDecl),
+    Helper(HelperDecl),
+    Control(ControlDecl),
+    Test(TestDecl),
+    Assert(AssertDecl),
+    Header(HeaderField),
+    Pragma(Pragma),
+    Comment(Comment),
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Pin(s) => s.span,
+            Statement::Node(s) => s.span,
+            Statement::Assignment(s) => s.span,
+            Statement::Table(s) => s.span,
+            Statement::Rom(s) => s.span,
+            Statement::Dff(s) => s.span,
+            Statement::Latch(s) => s.span,
+            Statement::HazardFree(s) => s.span,
+            Statement::Helper(s) => s.span,
+            Statement::Control(s) => s.span,
+            Statement::Test(s) => s.span,
+            Statement::Assert(s) => s.span,
+            Statement::Header(s) => s.span,
+            Statement::Pragma(s) => s.span,
+            Statement::Comment(s) => s.span,
+        }
+    }
+}
+
+/// `pin 1, 2 = a, b;`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct PinDecl {
+    pub names: Vec<Name>,
+    pub numbers: Vec<usize>,
+    pub span: Span,
+}
+
+/// `node state[0..3].dff;`, `dff` is set if the nodes are registers
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct NodeDecl {
+    pub names: Vec<Name>,
+    pub dff: bool,
+    pub span: Span,
+}
+
+/// `y = a & b;`, `y |= a & b;` if `merge` is set or the latch `y.latch(g) = a & b;`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Assignment {
+    pub targets: Vec<Name>,
+    pub merge: bool,
+    /// the gate of a latch
+    pub gate: Option<Expression>,
+    pub expression: Expression,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum TableMode {
+    Full,
+    Fill(bool),
+    Count,
+}
+
+/// `table(a, b -> y).fill(0) { 11 1 }`, `rows` holds every number in the block
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct TableDecl {
+    pub inputs: Vec<Name>,
+    pub outputs: Vec<Name>,
+    pub mode: TableMode,
+    pub rows: Vec<Bits>,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Bits {
+    pub bits: String,
+    pub span: Span,
+}
+
+/// `rom(a[0..3] -> d[0..7]) from "table.hex";`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct RomDecl {
+    pub inputs: Vec<Name>,
+    pub outputs: Vec<Name>,
+    pub file: String,
+    pub span: Span,
+}
+
+/// `q.dff;`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct DffDecl {
+    pub names: Vec<Name>,
+    pub span: Span,
+}
+
+/// `q.latch;`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct LatchDecl {
+    pub names: Vec<Name>,
+    pub span: Span,
+}
+
+/// `y.hazard_free;`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct HazardFreeDecl {
+    pub names: Vec<Name>,
+    pub span: Span,
+}
+
+/// `y.helper(20, 21);`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct HelperDecl {
+    pub names: Vec<Name>,
+    pub pins: Vec<usize>,
+    pub span: Span,
+}
+
+/// `y.oe = a & b;`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ControlDecl {
+    pub names: Vec<Name>,
+    pub control: Control,
+    pub expression: Expression,
+    pub span: Span,
+}
+
+/// `test { a b -> y; 0 1 -> L; }` the levels of a vector are kept as written without spaces
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct TestDecl {
+    pub inputs: Vec<Name>,
+    pub outputs: Vec<Name>,
+    pub vectors: Vec<VectorDecl>,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct VectorDecl {
+    pub inputs: String,
+    pub outputs: String,
+    pub span: Span,
+}
+
+/// `assert always (a | b) within 4;`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct AssertDecl {
+    pub property: Property,
+    pub expression: Expression,
+    pub span: Span,
+}
+
+/// the keys of a `HeaderField`, they are only keywords at the start of a statement followed by `=`
+/// so they can still be used as signal names
+pub const HEADER_KEYS: [&str; 7] = [
+    "name", "partno", "revision", "designer", "company", "date", "device",
+];
+
+/// the words which are only keywords in their place, at the start of a statement, after a `.` or
+/// inside `rom` and `assert`, everywhere else they are signal names
+pub const CONTEXTUAL_KEYWORDS: [&str; 11] = [
+    "node",
+    "latch",
+    "hazard_free",
+    "helper",
+    "rom",
+    "from",
+    "test",
+    "assert",
+    "always",
+    "eventually",
+    "within",
+];
+
+/// `name = "adder";` where `key` is one of `HEADER_KEYS`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderField {
+    pub key: String,
+    pub value: String,
+    pub span: Span,
+}
+
+/// `#pragma name_first` with the text after `#pragma`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Pragma {
+    pub text: String,
+    pub span: Span,
+}
+
+/// a `//` or `/* */` comment including the delimiters
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
+/// The tokens of an expression as written, precedence is left to `bool_algebra`.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Expression {
+    pub tokens: Vec<ExprToken>,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ExprToken {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum ExprKind {
+    And,
+    Or,
+    Xor,
+    Not,
+    Open,
+    Close,
+    Const(bool),
+    Var(Name),
+    /// `state == 0b10` or `state != 0b10`
+    Compare {
+        name: Name,
+        negate: bool,
+        bits: String,
+    },
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct SyntaxError {
+    pub msg: String,
+    pub span: Span,
+}
+
+impl Ast {
+    pub fn parse(code: &str) -> Result<Self, SyntaxError> {
+        Self::parse_with(code, ParserOptions::default())
+    }
+
+    /// parses with the given dialect, `#pragma` lines change it like they do for `OGal`
+    pub fn parse_with(code: &str, options: ParserOptions) -> Result<Self, SyntaxError> {
+        let mut tokens = Vec::new();
+        let mut comments = Vec::new();
+        for (token, span) in Token::lexer(code).spanned() {
+            let text = &code[span.clone()];
+            let span = Span::new(span.start, span.end);
+            match token {
+                Token::Unknown => {
+                    return Err(SyntaxError {
+                        msg: format!("unexpected {:?}", text),
+                        span,
+                    })
+                }
+                Token::Ignore(_) if text.starts_with("//") || text.starts_with("/*") => {
+                    let text = text.trim_end().to_string();
+                    let span = Span::new(span.start, span.start + text.len());
+                    comments.push(Comment { text, span });
+                }
+                Token::Ignore(_) => (),
+                token => tokens.push((token, span)),
+            }
+        }
+
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: Span::new(code.len(), code.len()),
+            options,
+        };
+        let mut statements = Vec::new();
+        while parser.pos < parser.tokens.len() {
+            statements.push(parser.statement()?);
+        }
+
+        for comment in comments {
+            let index = statements
+                .iter()
+                .position(|statement| statement.span().start > comment.span.start)
+                .unwrap_or(statements.len());
+            statements.insert(index, Statement::Comment(comment));
+        }
+
+        Ok(Self { statements })
+    }
+
+    pub fn accept<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        for statement in self.statements.iter() {
+            visitor.visit_statement(statement);
+        }
+    }
+}
+
+/// Walks the `Ast`, the default methods visit every `Name` so overriding `visit_name` is enough
+/// to find all definitions and uses of a signal.
+pub trait Visitor {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Pin(s) => self.visit_pin(s),
+            Statement::Node(s) => self.visit_node(s),
+            Statement::Assignment(s) => self.visit_assignment(s),
+            Statement::Table(s) => self.visit_table(s),
+            Statement::Rom(s) => self.visit_rom(s),
+            Statement::Dff(s) => self.visit_dff(s),
+            Statement::Latch(s) => self.visit_latch(s),
+            Statement::HazardFree(s) => self.visit_hazard_free(s),
+            Statement::Helper(s) => self.visit_helper(s),
+            Statement::Control(s) => self.visit_control(s),
+            Statement::Test(s) => self.visit_test(s),
+            Statement::Assert(s) => self.visit_assert(s),
+            Statement::Header(s) => self.visit_header(s),
+            Statement::Pragma(s) => self.visit_pragma(s),
+            Statement::Comment(s) => self.visit_comment(s),
+        }
+    }
+
+    fn visit_pin(&mut self, pin: &PinDecl) {
+        pin.names.iter().for_each(|name| self.visit_name(name));
+    }
+
+    fn visit_node(&mut self, node: &NodeDecl) {
+        node.names.iter().for_each(|name| self.visit_name(name));
+    }
+
+    fn visit_assignment(&mut self, assignment: &Assignment) {
+        assignment
+            .targets
+            .iter()
+            .for_each(|name| self.visit_name(name));
+        if let Some(gate) = &assignment.gate {
+            self.visit_expression(gate);
+        }
+        self.visit_expression(&assignment.expression);
+    }
+
+    fn visit_table(&mut self, table: &TableDecl) {
+        table.inputs.iter().for_each(|name| self.visit_name(name));
+        table.outputs.iter().for_each(|name| self.visit_name(name));
+    }
+
+    fn visit_rom(&mut self, rom: &RomDecl) {
+        rom.inputs.iter().for_each(|name| self.visit_name(name));
+        rom.outputs.iter().for_each(|name| self.visit_name(name));
+    }
+
+    fn visit_dff(&mut self, dff: &DffDecl) {
+        dff.names.iter().for_each(|name| self.visit_name(name));
+    }
+
+    fn visit_latch(&mut self, latch: &LatchDecl) {
+        latch.names.iter().for_each(|name| self.visit_name(name));
+    }
+
+    fn visit_hazard_free(&mut self, hazard_free: &HazardFreeDecl) {
+        hazard_free
+            .names
+            .iter()
+            .for_each(|name| self.visit_name(name));
+    }
+
+    fn visit_helper(&mut self, helper: &HelperDecl) {
+        helper.names.iter().for_each(|name| self.visit_name(name));
+    }
+
+    fn visit_control(&mut self, control: &ControlDecl) {
+        control.names.iter().for_each(|name| self.visit_name(name));
+        self.visit_expression(&control.expression);
+    }
+
+    fn visit_test(&mut self, test: &TestDecl) {
+        test.inputs.iter().for_each(|name| self.visit_name(name));
+        test.outputs.iter().for_each(|name| self.visit_name(name));
+    }
+
+    fn visit_assert(&mut self, assert: &AssertDecl) {
+        self.visit_expression(&assert.expression);
+    }
+
+    fn visit_header(&mut self, _header: &HeaderField) {}
+
+    fn visit_pragma(&mut self, _pragma: &Pragma) {}
+
+    fn visit_comment(&mut self, _comment: &Comment) {}
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        for token in expression.tokens.iter() {
+            match &token.kind {
+                ExprKind::Var(name) | ExprKind::Compare { name, .. } => self.visit_name(name),
+                _ => (),
+            }
+        }
+    }
+
+    fn visit_name(&mut self, _name: &Name) {}
+}
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    pos: usize,
+    /// the empty span at the end of the source
+    end: Span,
+    options: ParserOptions,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + n).map(|(token, _)| token)
+    }
+
+    fn next_is(&self, token: Token) -> bool {
+        self.peek().is_some_and(|next| next.type_eq(&token))
+    }
+
+    /// the `n`th next token is the identifier `word`, see `CONTEXTUAL_KEYWORDS`
+    fn keyword_at(&self, n: usize, word: &str) -> bool {
+        matches!(self.peek_nth(n), Some(Token::Identifier(ident)) if ident == word)
+    }
+
+    /// the `n`th next token starts an operand, `a != 0b1` compares `a` and doesn't negate
+    fn operand_at(&self, n: usize) -> bool {
+        match self.peek_nth(n) {
+            Some(Token::Identifier(_)) | Some(Token::Number(_)) | Some(Token::RoundOpen) => true,
+            Some(Token::Not) => self.peek_nth(n + 1) != Some(&Token::Equals),
+            _ => false,
+        }
+    }
+
+    /// the next token for error messages
+    fn found(&self) -> String {
+        self.peek()
+            .map_or_else(|| "the end of the file".to_string(), describe)
+    }
+
+    fn span(&self) -> Span {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |&(_, span)| span)
+    }
+
+    /// the end of the last consumed token
+    fn last(&self) -> Span {
+        match self.pos {
+            0 => Span::default(),
+            pos => self.tokens[pos - 1].1,
+        }
+    }
+
+    fn error(&self, msg: &str) -> SyntaxError {
+        SyntaxError {
+            msg: msg.to_string(),
+            span: self.span(),
+        }
+    }
+
+    fn next(&mut self) -> Result<(Token, Span), SyntaxError> {
+        match self.tokens.get(self.pos) {
+            Some(next) => {
+                self.pos += 1;
+                Ok(next.clone())
+            }
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(Token, Span), SyntaxError> {
+        if self.next_is(token.clone()) {
+            self.next()
+        } else {
+            Err(self.error(&format!(
+                "expected {} but found {}",
+                describe(&token),
+                self.found()
+            )))
+        }
+    }
+
+    fn expect_keyword(&mut self, word: &str) -> Result<(Token, Span), SyntaxError> {
+        if self.keyword_at(0, word) {
+            self.next()
+        } else {
+            Err(self.error(&format!("expected `{}` but found {}", word, self.found())))
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, SyntaxError> {
+        let start = self.span();
+        let statement = match self.peek().cloned() {
+            Some(Token::Pin) => self.pin(start)?,
+            Some(Token::Identifier(word))
+                if word == "node" && matches!(self.peek_nth(1), Some(Token::Identifier(_))) =>
+            {
+                self.next()?;
+                let names = self.names()?;
+                let dff = self.next_is(Token::Dot);
+                if dff {
+                    self.next()?;
+                    self.expect(Token::Dff)?;
+                }
+                self.expect(Token::Semicolon)?;
+                Statement::Node(NodeDecl {
+                    names,
+                    dff,
+                    span: start.to(self.last()),
+                })
+            }
+            Some(Token::Table) => self.table(start)?,
+            Some(Token::Identifier(word))
+                if word == "rom" && self.peek_nth(1) == Some(&Token::RoundOpen) =>
+            {
+                self.rom(start)?
+            }
+            Some(Token::Identifier(word))
+                if word == "test" && self.peek_nth(1) == Some(&Token::CurlyOpen) =>
+            {
+                self.test(start)?
+            }
+            // `assert = a;` or `assert.dff;` use a signal named assert
+            Some(Token::Identifier(word))
+                if word == "assert"
+                    && !matches!(
+                        self.peek_nth(1),
+                        None | Some(Token::Equals)
+                            | Some(Token::OrEquals)
+                            | Some(Token::Dot)
+                            | Some(Token::Comma)
+                            | Some(Token::SquareOpen)
+                    ) =>
+            {
+                self.assert(start)?
+            }
+            Some(Token::Pragma(text)) => {
+                self.next()?;
+                if let Err(msg) = self.options.pragma(&text) {
+                    return Err(SyntaxError { msg, span: start });
+                }
+                Statement::Pragma(Pragma { text, span: start })
+            }
+            Some(Token::Identifier(key))
+                if HEADER_KEYS.contains(&key.as_str())
+                    && self.peek_nth(1) == Some(&Token::Equals) =>
+            {
+                self.next()?;
+                self.expect(Token::Equals)?;
+                let value = match self.expect(Token::Str(String::new()))? {
+                    (Token::Str(value), _) => value,
+                    _ => unreachable!(),
+                };
+                self.expect(Token::Semicolon)?;
+                Statement::Header(HeaderField {
+                    key,
+                    value,
+                    span: start.to(self.last()),
+                })
+            }
+            Some(Token::Identifier(_)) => {
+                let names = self.names()?;
+                if self.next_is(Token::Dot) {
+                    self.next()?;
+                    let control = match self.peek() {
+                        Some(Token::Identifier(name)) => Control::from_name(name),
+                        _ => None,
+                    };
+                    if let Some(control) = control {
+                        self.next()?;
+                        self.expect(Token::Equals)?;
+                        let expression = self.expression(&[Token::Semicolon])?;
+                        self.expect(Token::Semicolon)?;
+                        return Ok(Statement::Control(ControlDecl {
+                            names,
+                            control,
+                            expression,
+                            span: start.to(self.last()),
+                        }));
+                    }
+                    if self.keyword_at(0, "latch") {
+                        self.next()?;
+                        if self.next_is(Token::RoundOpen) {
+                            self.next()?;
+                            let gate = self.expression(&[Token::RoundClose])?;
+                            self.expect(Token::RoundClose)?;
+                            self.expect(Token::Equals)?;
+                            let expression = self.expression(&[Token::Semicolon])?;
+                            self.expect(Token::Semicolon)?;
+                            return Ok(Statement::Assignment(Assignment {
+                                targets: names,
+                                merge: false,
+                                gate: Some(gate),
+                                expression,
+                                span: start.to(self.last()),
+                            }));
+                        }
+                        self.expect(Token::Semicolon)?;
+                        Statement::Latch(LatchDecl {
+                            names,
+                            span: start.to(self.last()),
+                        })
+                    } else if self.keyword_at(0, "helper") {
+                        self.next()?;
+                        self.expect(Token::RoundOpen)?;
+                        let pins = self.numbers()?;
+                        self.expect(Token::RoundClose)?;
+                        self.expect(Token::Semicolon)?;
+                        Statement::Helper(HelperDecl {
+                            names,
+                            pins,
+                            span: start.to(self.last()),
+                        })
+                    } else if self.keyword_at(0, "hazard_free") {
+                        self.next()?;
+                        self.expect(Token::Semicolon)?;
+                        Statement::HazardFree(HazardFreeDecl {
+                            names,
+                            span: start.to(self.last()),
+                        })
+                    } else {
+                        self.expect(Token::Dff)?;
+                        self.expect(Token::Semicolon)?;
+                        Statement::Dff(DffDecl {
+                            names,
+                            span: start.to(self.last()),
+                        })
+                    }
+                } else {
+                    let merge = self.next_is(Token::OrEquals);
+                    if merge {
+                        self.next()?;
+                    } else {
+                        self.expect(Token::Equals)?;
+                    }
+                    let expression = self.expression(&[Token::Semicolon])?;
+                    self.expect(Token::Semicolon)?;
+                    Statement::Assignment(Assignment {
+                        targets: names,
+                        merge,
+                        gate: None,
+                        expression,
+                        span: start.to(self.last()),
+                    })
+                }
+            }
+            _ => return Err(self.error(&format!("unexpected {}", self.found()))),
+        };
+        Ok(statement)
+    }
+
+    fn pin(&mut self, start: Span) -> Result<Statement, SyntaxError> {
+        self.expect(Token::Pin)?;
+        let (numbers, names) = if self.options.num_first {
+            let numbers = self.numbers()?;
+            self.expect(Token::Equals)?;
+            (numbers, self.names()?)
+        } else {
+            let names = self.names()?;
+            self.expect(Token::Equals)?;
+            (self.numbers()?, names)
+        };
+        self.expect(Token::Semicolon)?;
+
+        let span = start.to(self.last());
+        if numbers.len() != names.len() {
+            return Err(SyntaxError {
+                msg: format!(
+                    "pin name len ({}) and pin number len ({}) doesn't match",
+                    names.len(),
+                    numbers.len()
+                ),
+                span,
+            });
+        }
+        Ok(Statement::Pin(PinDecl {
+            names,
+            numbers,
+            span,
+        }))
+    }
+
+    fn table(&mut self, start: Span) -> Result<Statement, SyntaxError> {
+        self.expect(Token::Table)?;
+        let (inputs, outputs) = self.signature()?;
+
+        let mut mode = TableMode::Full;
+        if self.next_is(Token::Dot) {
+            self.next()?;
+            if self.next_is(Token::Count) {
+                self.next()?;
+                mode = TableMode::Count;
+            } else {
+                self.expect(Token::Fill)?;
+                self.expect(Token::RoundOpen)?;
+                mode = match self.expect(Token::Number(String::new()))? {
+                    (Token::Number(num), _) if num == "0" => TableMode::Fill(false),
+                    (Token::Number(num), _) if num == "1" => TableMode::Fill(true),
+                    (_, span) => {
+                        return Err(SyntaxError {
+                            msg: "only '0' or '1' allowed".to_string(),
+                            span,
+                        })
+                    }
+                };
+                self.expect(Token::RoundClose)?;
+            }
+        }
+
+        self.expect(Token::CurlyOpen)?;
+        let mut rows = Vec::new();
+        while self.next_is(Token::Number(String::new())) {
+            if let (Token::Number(bits), span) = self.next()? {
+                if bits.chars().any(|c| c != '0' && c != '1') {
+                    return Err(SyntaxError {
+                        msg: format!("unexpected {} only '0' or '1' allowed", bits),
+                        span,
+                    });
+                }
+                rows.push(Bits { bits, span });
+            }
+        }
+        self.expect(Token::CurlyClose)?;
+
+        Ok(Statement::Table(TableDecl {
+            inputs,
+            outputs,
+            mode,
+            rows,
+            span: start.to(self.last()),
+        }))
+    }
+
+    fn rom(&mut self, start: Span) -> Result<Statement, SyntaxError> {
+        self.expect_keyword("rom")?;
+        let (inputs, outputs) = self.signature()?;
+        self.expect_keyword("from")?;
+        let file = match self.expect(Token::Str(String::new()))? {
+            (Token::Str(file), _) => file,
+            _ => unreachable!(),
+        };
+        self.expect(Token::Semicolon)?;
+
+        Ok(Statement::Rom(RomDecl {
+            inputs,
+            outputs,
+            file,
+            span: start.to(self.last()),
+        }))
+    }
+
+    /// `(a, b -> y)`
+    fn signature(&mut self) -> Result<(Vec<Name>, Vec<Name>), SyntaxError> {
+        self.expect(Token::RoundOpen)?;
+        let inputs = self.names()?;
+        self.expect(Token::Arrow)?;
+        let outputs = self.names()?;
+        self.expect(Token::RoundClose)?;
+        Ok((inputs, outputs))
+    }
+
+    fn test(&mut self, start: Span) -> Result<Statement, SyntaxError> {
+        self.expect_keyword("test")?;
+        self.expect(Token::CurlyOpen)?;
+
+        let mut inputs = Vec::new();
+        while !self.next_is(Token::Arrow) {
+            inputs.append(&mut self.names()?);
+        }
+        self.expect(Token::Arrow)?;
+        let mut outputs = Vec::new();
+        while !self.next_is(Token::Semicolon) {
+            outputs.append(&mut self.names()?);
+        }
+        self.expect(Token::Semicolon)?;
+
+        let mut vectors = Vec::new();
+        while !self.next_is(Token::CurlyClose) {
+            let span = self.span();
+            let ins = self.levels(Token::Arrow)?;
+            let outs = self.levels(Token::Semicolon)?;
+            vectors.push(VectorDecl {
+                inputs: ins,
+                outputs: outs,
+                span: span.to(self.last()),
+            });
+        }
+        self.expect(Token::CurlyClose)?;
+
+        Ok(Statement::Test(TestDecl {
+            inputs,
+            outputs,
+            vectors,
+            span: start.to(self.last()),
+        }))
+    }
+
+    fn levels(&mut self, end: Token) -> Result<String, SyntaxError> {
+        let mut result = String::new();
+        loop {
+            match self.peek() {
+                Some(Token::Number(chars)) | Some(Token::Identifier(chars)) => {
+                    result.push_str(chars);
+                    self.next()?;
+                }
+                _ => {
+                    self.expect(end)?;
+                    return Ok(result);
+                }
+            }
+        }
+    }
+
+    fn assert(&mut self, start: Span) -> Result<Statement, SyntaxError> {
+        self.expect_keyword("assert")?;
+        // `assert always;` checks a signal named always
+        let temporal = match self.peek() {
+            Some(Token::Identifier(word))
+                if (word == "always" || word == "eventually") && self.operand_at(1) =>
+            {
+                Some(word.clone())
+            }
+            _ => None,
+        };
+        if temporal.is_some() {
+            self.next()?;
+        }
+
+        let within = |parser: &Self| {
+            parser.keyword_at(0, "within") && matches!(parser.peek_nth(1), Some(Token::Number(_)))
+        };
+        let expression =
+            self.expression_until(|parser| parser.next_is(Token::Semicolon) || within(parser))?;
+        let bound = if within(self) {
+            self.next()?;
+            Some(self.number()?.0)
+        } else {
+            None
+        };
+        self.expect(Token::Semicolon)?;
+        let span = start.to(self.last());
+
+        let property = match (temporal.as_deref(), bound) {
+            (None, None) => Property::Invariant,
+            (Some("always"), bound) => Property::Always(bound),
+            (Some(_), Some(bound)) => Property::Eventually(bound),
+            (None, Some(_)) => {
+                return Err(SyntaxError {
+                    msg: "only `always` and `eventually` can have a bound".to_string(),
+                    span,
+                })
+            }
+            (Some(_), None) => {
+                return Err(SyntaxError {
+                    msg: "assert eventually needs a bound e.g. `within 8`".to_string(),
+                    span,
+                })
+            }
+        };
+
+        Ok(Statement::Assert(AssertDecl {
+            property,
+            expression,
+            span,
+        }))
+    }
+
+    /// reads up to (not including) one of the `end` tokens
+    fn expression(&mut self, end: &[Token]) -> Result<Expression, SyntaxError> {
+        self.expression_until(|parser| end.iter().any(|end| parser.next_is(end.clone())))
+    }
+
+    /// reads up to (not including) the first token where `end` is true
+    fn expression_until(&mut self, end: impl Fn(&Self) -> bool) -> Result<Expression, SyntaxError> {
+        let start = self.span();
+        let mut tokens = Vec::new();
+
+        while !end(self) {
+            let (token, span) = self.next()?;
+            let kind = match token {
+                Token::And => ExprKind::And,
+                Token::Or => ExprKind::Or,
+                Token::Xor => ExprKind::Xor,
+                Token::Not => ExprKind::Not,
+                Token::CuplOr | Token::CuplXor if !self.options.cupl_operators => {
+                    return Err(SyntaxError {
+                        msg: "'#' and '$' are only allowed after `#pragma cupl_operators`"
+                            .to_string(),
+                        span,
+                    })
+                }
+                Token::CuplOr => ExprKind::Or,
+                Token::CuplXor => ExprKind::Xor,
+                Token::RoundOpen => ExprKind::Open,
+                Token::RoundClose => ExprKind::Close,
+                Token::Number(num) if num == "0" => ExprKind::Const(false),
+                Token::Number(num) if num == "1" => ExprKind::Const(true),
+                Token::Identifier(name)
+                    if self.next_is(Token::Equals) || self.next_is(Token::Not) =>
+                {
+                    let name = Name { name, span };
+                    let negate = self.next()?.0 == Token::Not;
+                    self.expect(Token::Equals)?;
+                    let bits = match self.expect(Token::Binary(String::new()))? {
+                        (Token::Binary(bits), _) => bits,
+                        _ => unreachable!(),
+                    };
+                    tokens.push(ExprToken {
+                        kind: ExprKind::Compare { name, negate, bits },
+                        span: span.to(self.last()),
+                    });
+                    continue;
+                }
+                Token::Identifier(name) => ExprKind::Var(Name { name, span }),
+                other => {
+                    return Err(SyntaxError {
+                        msg: format!("unexpected {} in expression", describe(&other)),
+                        span,
+                    })
+                }
+            };
+            tokens.push(ExprToken { kind, span });
+        }
+
+        let span = match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => first.span.to(last.span),
+            _ => Span::new(start.start, start.start),
+        };
+        Ok(Expression { tokens, span })
+    }
+
+    /// `a, b`, `a[0..3]` or a single name
+    fn names(&mut self) -> Result<Vec<Name>, SyntaxError> {
+        let (name, span) = match self.expect(Token::Identifier(String::new()))? {
+            (Token::Identifier(name), span) => (name, span),
+            _ => unreachable!(),
+        };
+
+        if self.next_is(Token::SquareOpen) {
+            let nums = self.numbers()?;
+            let span = span.to(self.last());
+            return Ok(nums
+                .iter()
+                .map(|num| Name {
+                    name: format!("{}{}", name, num),
+                    span,
+                })
+                .collect());
+        }
+
+        let mut result = vec![Name { name, span }];
+        while self.next_is(Token::Comma) {
+            self.next()?;
+            match self.expect(Token::Identifier(String::new()))? {
+                (Token::Identifier(name), span) => result.push(Name { name, span }),
+                _ => unreachable!(),
+            }
+        }
+        Ok(result)
+    }
+
+    /// `[1..3]`, `1, 2` or a single number
+    fn numbers(&mut self) -> Result<Vec<usize>, SyntaxError> {
+        if self.next_is(Token::SquareOpen) {
+            self.next()?;
+            let (start, _) = self.number()?;
+            self.expect(Token::Dot)?;
+            self.expect(Token::Dot)?;
+            let (end, _) = self.number()?;
+            self.expect(Token::SquareClose)?;
+            return Ok((start..end + 1).collect());
+        }
+
+        let mut result = vec![self.number()?.0];
+        while self.next_is(Token::Comma) {
+            self.next()?;
+            result.push(self.number()?.0);
+        }
+        Ok(result)
+    }
+
+    fn number(&mut self) -> Result<(usize, Span), SyntaxError> {
+        match self.expect(Token::Number(String::new()))? {
+            (Token::Number(num), span) => match num.parse() {
+                Ok(num) => Ok((num, span)),
+                Err(err) => Err(SyntaxError {
+                    msg: format!("ParseIntError: {}", err),
+                    span,
+                }),
+            },
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// a token for error messages, the empty `Identifier("")` etc. passed to `expect` stand for any
+fn describe(token: &Token) -> String {
+    let text = match token {
+        Token::Identifier(name) if name.is_empty() => return "a name".to_string(),
+        Token::Number(num) if num.is_empty() => return "a number".to_string(),
+        Token::Binary(bits) if bits.is_empty() => return "a binary number e.g. `0b01`".to_string(),
+        Token::Str(text) if text.is_empty() => return "a string".to_string(),
+        Token::Identifier(name) | Token::Number(name) => name.as_str(),
+        Token::Binary(bits) => return format!("`0b{}`", bits),
+        Token::Str(text) => return format!("\"{}\"", text),
+        Token::Pragma(_) => return "a `#pragma`".to_string(),
+        Token::Ignore(_) | Token::Unknown => return "an unknown character".to_string(),
+        Token::Pin => "pin",
+        Token::Table => "table",
+        Token::Count => "count",
+        Token::Fill => "fill",
+        Token::Dff => "dff",
+        Token::Comma => ",",
+        Token::Semicolon => ";",
+        Token::Equals => "=",
+        Token::Dot => ".",
+        Token::And => "&",
+        Token::Or => "|",
+        Token::OrEquals => "|=",
+        Token::Xor => "^",
+        Token::CuplOr => "#",
+        Token::CuplXor => "$",
+        Token::Not => "!",
+        Token::CurlyOpen => "{",
+        Token::RoundOpen => "(",
+        Token::SquareOpen => "[",
+        Token::CurlyClose => "}",
+        Token::RoundClose => ")",
+        Token::SquareClose => "]",
+        Token::Arrow => "->",
+    };
+    format!("`{}`", text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position() {
+        let code = "pin 1 = a;\n  y = a;";
+        assert_eq!(super::position(code, 0), (0, 0));
+        assert_eq!(super::position(code, 13), (1, 2));
+    }
+
+    #[test]
+    fn spans() {
+        let code = "// in\npin [1..2] = a[0..1];\ny = !a0 | a1 == 0b1;";
+        let ast = Ast::parse(code).unwrap();
+
+        assert_eq!(ast.statements.len(), 3);
+        assert_eq!(
+            ast.statements[0],
+            Statement::Comment(Comment {
+                text: "// in".to_string(),
+                span: Span::new(0, 5)
+            })
+        );
+        match &ast.statements[1] {
+            Statement::Pin(pin) => {
+                assert_eq!(pin.numbers, vec![1, 2]);
+                assert_eq!(
+                    &code[pin.names[1].span.start..pin.names[1].span.end],
+                    "a[0..1]"
+                );
+            }
+            other => panic!("expected pin but got {:?}", other),
+        }
+        match &ast.statements[2] {
+            Statement::Assignment(assignment) => {
+                let span = assignment.expression.span;
+                assert_eq!(&code[span.start..span.end], "!a0 | a1 == 0b1");
+                assert_eq!(assignment.expression.tokens.len(), 4);
+            }
+            other => panic!("expected assignment but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn contextual_keywords() {
+        let code = "pin 1, 2, 3 = test, from, always;\npin 4 = assert;\nnode node;\n\
+                    assert always always & from within 2;\nassert always;\n";
+        let ast = Ast::parse(code).unwrap();
+
+        let names: Vec<&str> = match &ast.statements[0] {
+            Statement::Pin(pin) => pin.names.iter().map(|name| name.name.as_str()).collect(),
+            other => panic!("expected pin but got {:?}", other),
+        };
+        assert_eq!(names, vec!["test", "from", "always"]);
+        match &ast.statements[2] {
+            Statement::Node(node) => assert_eq!(node.names[0].name, "node"),
+            other => panic!("expected node but got {:?}", other),
+        }
+        match &ast.statements[3] {
+            Statement::Assert(assert) => {
+                assert_eq!(assert.property, Property::Always(Some(2)));
+                assert_eq!(assert.expression.tokens.len(), 3);
+            }
+            other => panic!("expected assert but got {:?}", other),
+        }
+        match &ast.statements[4] {
+            Statement::Assert(assert) => {
+                assert_eq!(assert.property, Property::Invariant);
+                assert_eq!(assert.expression.tokens.len(), 1);
+            }
+            other => panic!("expected assert but got {:?}", other),
+        }
+        assert!(Ast::parse("assert = test;\nrom.dff;\nwithin |= from;\n").is_ok());
+    }
+
+    #[test]
+    fn messages() {
+        let msg = |code| Ast::parse(code).unwrap_err().msg;
+        assert_eq!(msg("pin 2 = ;"), "expected a name but found `;`");
+        assert_eq!(
+            msg("pin 2 = a"),
+            "expected `;` but found the end of the file"
+        );
+        assert_eq!(msg("y = a -> b;"), "unexpected `->` in expression");
+        assert_eq!(
+            msg("rom(a -> y) \"x.bin\";"),
+            "expected `from` but found \"x.bin\""
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use super::ast::{Ast, Statement};
+use super::{OGal, ParserOptions};
+use crate::translator::dnf::hazard_free_cover;
+use crate::CircuitConfig;
+use hardware_sim::Component;
+
+impl OGal {
+    /// Assigns a pin to every signal without `pin`, the pins which are already assigned stay
+    /// locked. Outputs are placed first, the ones with the most product terms on the free OLMC
+    /// with the fewest terms that still holds them. A `node` only goes to a buried OLMC and the
+    /// other signals never do, and a registered output only goes to an OLMC which can be
+    /// registered. Inputs go to input only pins, then special pins and then the OLMCs
+    /// which are left. The clock pin is kept free if the design has registers. Returns the new
+    /// assignments in the order they were made, see `write_pins`.
+    pub fn fit(&mut self, config: &CircuitConfig) -> Result<Vec<(String, usize)>, String> {
+        // the terms of every output for an active high and an active low OLMC
+        let mut outputs: Vec<(String, [usize; 2])> = Vec::new();
+        for lut in self.lut.iter() {
+            for (name, table) in lut.out_names().into_iter().zip(lut.get_table()) {
+                let inverted: Vec<bool> = table.iter().map(|&val| !val).collect();
+                let terms = if self.latch.contains(&name) || self.hazard_free.contains(&name) {
+                    [
+                        hazard_free_cover(&table).len(),
+                        hazard_free_cover(&inverted).len(),
+                    ]
+                } else {
+                    let ones = table.iter().filter(|&&val| val).count();
+                    [ones, table.len() - ones]
+                };
+                outputs.push((name, terms));
+            }
+        }
+
+        let mut inputs: Vec<String> = Vec::new();
+        let mut feedback: Vec<String> = Vec::new();
+        let controls = self.controls.iter().map(|(_, _, lut)| lut);
+        for lut in self.lut.iter().chain(controls) {
+            for name in lut.in_names() {
+                if outputs.iter().any(|(output, _)| *output == name) {
+                    feedback.push(name);
+                } else if !inputs.contains(&name) {
+                    inputs.push(name);
+                }
+            }
+        }
+
+        let mut taken: Vec<u32> = self.pins.values().map(|&pin| pin as u32).collect();
+        for (_, pins) in self.helper.iter() {
+            taken.extend(pins.iter().map(|&pin| pin as u32));
+        }
+        if let Some(clock) = config.clock {
+            if !self.dff.is_empty() && !taken.contains(&clock) {
+                taken.push(clock);
+            }
+        }
+
+        let architecture = config.architecture();
+        let mut assigned = Vec::new();
+
+        // the terms an output needs on the OLMC of `pin`
+        let needs = |name: &String, terms: &[usize; 2], pin: u32| {
+            let active_low = architecture
+                .olmc(pin)
+                .and_then(|olmc| olmc.mode(self.dff.contains(name)))
+                .is_some_and(|mode| mode.active_low);
+            terms[active_low as usize]
+        };
+        // the fewest terms an output needs on any OLMC
+        let fewest = |name: &String, terms: &[usize; 2]| {
+            config
+                .outputs
+                .iter()
+                .map(|&(pin, _)| needs(name, terms, pin))
+                .min()
+                .unwrap_or(terms[0])
+        };
+
+        let mut unplaced: Vec<&(String, [usize; 2])> = outputs
+            .iter()
+            .filter(|(name, _)| !self.pins.contains_key(name))
+            .collect();
+        unplaced.sort_by_key(|(name, terms)| std::cmp::Reverse(fewest(name, terms)));
+        for (name, terms) in unplaced {
+            let node = self.nodes.contains(name);
+            if node && config.nodes.is_empty() {
+                return Err(format!(
+                    "{} is a node but the device has no buried OLMCs",
+                    name
+                ));
+            }
+            let mut free: Vec<(u32, u32)> = config
+                .outputs
+                .iter()
+                .filter(|&&(pin, _)| !taken.contains(&pin))
+                .filter(|&&(pin, _)| config.nodes.contains(&pin) == node)
+                .filter(|&&(pin, _)| {
+                    let registered = self.dff.contains(name);
+                    match architecture.olmc(pin) {
+                        Some(olmc) => olmc.mode(registered).is_some(),
+                        None => true,
+                    }
+                })
+                .filter(|&&(pin, _)| !feedback.contains(name) || config.inputs.contains(&pin))
+                .map(|&(pin, max)| {
+                    let registered = self.dff.contains(name);
+                    (pin, config.terms(pin, registered).unwrap_or(max))
+                })
+                .collect();
+            free.sort_by_key(|&(pin, max)| (max, pin));
+
+            // an output which doesn't fit anywhere gets the biggest OLMC and is split from there
+            let pin = match free
+                .iter()
+                .find(|&&(pin, max)| max as usize >= needs(name, terms, pin))
+                .or_else(|| free.last())
+            {
+                Some(&(pin, _)) => pin,
+                None if self.dff.contains(name) => {
+                    return Err(format!("no OLMC left which can register {}", name))
+                }
+                None => {
+                    return Err(format!(
+                        "no OLMC left for {} with {} terms",
+                        name,
+                        fewest(name, terms)
+                    ))
+                }
+            };
+            taken.push(pin);
+            assigned.push((name.clone(), pin as usize));
+        }
+
+        let olmcs: Vec<u32> = config.outputs.iter().map(|&(pin, _)| pin).collect();
+        let mut free: Vec<u32> = config
+            .inputs
+            .iter()
+            .filter(|pin| !olmcs.contains(pin))
+            .cloned()
+            .collect();
+        free.extend(config.special_pins.iter().map(|&(pin, _)| pin));
+        free.extend(config.inputs.iter().filter(|pin| olmcs.contains(pin)));
+        free.retain(|pin| !taken.contains(pin) && !config.nodes.contains(pin));
+
+        let mut free = free.into_iter();
+        for name in inputs.iter().filter(|name| !self.pins.contains_key(*name)) {
+            match free.next() {
+                Some(pin) => assigned.push((name.clone(), pin as usize)),
+                None => return Err(format!("no pin left for the input {}", name)),
+            }
+        }
+
+        for (name, pin) in assigned.iter() {
+            self.pins.insert(name.clone(), *pin);
+        }
+        Ok(assigned)
+    }
+}
+
+/// Adds `pin` statements for the assignments of `OGal::fit` to the source, after the last `pin`
+/// statement or at the top if there is none.
+pub fn write_pins(
+    code: &str,
+    options: ParserOptions,
+    pins: &[(String, usize)],
+) -> Result<String, String> {
+    let num_first = options.num_first;
+    let ast = match Ast::parse_with(code, options) {
+        Ok(ast) => ast,
+        Err(err) => return Err(err.msg),
+    };
+    let end = ast
+        .statements
+        .iter()
+        .rev()
+        .find_map(|statement| match statement {
+            Statement::Pin(pin) => Some(pin.span.end),
+            _ => None,
+        });
+
+    let lines: Vec<String> = pins
+        .iter()
+        .map(|(name, pin)| {
+            if num_first {
+                format!("pin {} = {};", pin, name)
+            } else {
+                format!("pin {} = {};", name, pin)
+            }
+        })
+        .collect();
+    if lines.is_empty() {
+        return Ok(code.to_string());
+    }
+
+    Ok(match end {
+        Some(end) => {
+            let line_start = code[..end].rfind('\n').map_or(0, |i| i + 1);
+            let indent: String = code[line_start..]
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .collect();
+            let mut result = code[..end].to_string();
+            for line in lines {
+                result.push('\n');
+                result.push_str(&indent);
+                result.push_str(&line);
+            }
+            result.push_str(&code[end..]);
+            result
+        }
+        None => format!("{}\n\n{}", lines.join("\n"), code),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_pins;
+    use crate::ParserOptions;
+
+    #[test]
+    fn write_back() {
+        let pins = vec![("y".to_string(), 14), ("b".to_string(), 2)];
+        assert_eq!(
+            write_pins("pin 1 = a;\ny = a & b;\n", ParserOptions::new(), &pins),
+            Ok("pin 1 = a;\npin 14 = y;\npin 2 = b;\ny = a & b;\n".to_string())
+        );
+        assert_eq!(
+            write_pins("y = b;\n", ParserOptions::new(), &pins[..1]),
+            Ok("pin 14 = y;\n\ny = b;\n".to_string())
+        );
+    }
+}
@@ -0,0 +1,473 @@
+use super::{OGal, Token};
+use crate::assertion::Property;
+use crate::translator::dnf::prime_implicants;
+use hardware_sim::{Component, LookupTable};
+use logos::Logos;
+
+/// Formats the source of a design the canonical way.
+///
+/// Every statement gets its own line with single spaces around operators, comments are kept
+/// where they are and the columns of `table` and `test` blocks are aligned. One blank line between
+/// statements is kept, more are collapsed.
+pub fn format_code(code: &str) -> Result<String, String> {
+    let items = items(code)?;
+    let mut formatter = Formatter::default();
+
+    let mut i = 0;
+    while i < items.len() {
+        let item = &items[i];
+        let merged = match (
+            &item.token,
+            items.get(i + 1).and_then(|next| next.token.as_ref()),
+        ) {
+            (Some(Token::Not), Some(Token::Equals)) if formatter.block.is_none() => Some("!="),
+            (Some(Token::Equals), Some(Token::Equals)) if formatter.block.is_none() => Some("=="),
+            _ => None,
+        };
+
+        match (&item.token, merged) {
+            (None, _) => formatter.comment(&item.text, item.newlines),
+            (Some(_), Some(op)) => {
+                formatter.push(&Token::Equals, op, item.newlines);
+                i += 1;
+            }
+            (Some(token), None) => formatter.push(token, &item.text, item.newlines),
+        }
+        i += 1;
+    }
+
+    Ok(formatter.finish())
+}
+
+impl OGal {
+    /// prints the design as source code, expressions come out as full `table` blocks
+    pub fn to_source(&self) -> String {
+        let mut code = String::new();
+
+        let header = [
+            ("name", &self.header.name),
+            ("partno", &self.header.partno),
+            ("date", &self.header.date),
+            ("revision", &self.header.revision),
+            ("designer", &self.header.designer),
+            ("company", &self.header.company),
+            ("device", &self.header.device),
+        ];
+        for (key, val) in header.iter() {
+            if let Some(val) = val {
+                code.push_str(&format!("{} = \"{}\";\n", key, val));
+            }
+        }
+        if !self.header.is_empty() {
+            code.push('\n');
+        }
+
+        let mut pins: Vec<(&String, &usize)> = self.pins.iter().collect();
+        pins.sort_by_key(|&(name, num)| (*num, name.clone()));
+        for (name, num) in pins {
+            code.push_str(&format!("pin {} = {};\n", num, name));
+        }
+
+        for name in self.nodes.iter() {
+            code.push_str(&format!("\nnode {};", name));
+        }
+        for name in self.dff.iter() {
+            code.push_str(&format!("\n{}.dff;", name));
+        }
+        for name in self.latch.iter() {
+            code.push_str(&format!("\n{}.latch;", name));
+        }
+        for name in self.hazard_free.iter() {
+            code.push_str(&format!("\n{}.hazard_free;", name));
+        }
+        for (name, pins) in self.helper.iter() {
+            let pins: Vec<String> = pins.iter().map(|pin| pin.to_string()).collect();
+            code.push_str(&format!("\n{}.helper({});", name, pins.join(", ")));
+        }
+        for (name, control, lut) in self.controls.iter() {
+            code.push_str(&format!(
+                "\n{}.{} = {};",
+                name,
+                control.name(),
+                product_term(lut)
+            ));
+        }
+
+        for lut in self.lut.iter() {
+            let in_names = lut.in_names();
+            let table = lut.get_table();
+            code.push_str(&format!(
+                "\ntable({} -> {}) {{\n",
+                in_names.join(", "),
+                lut.out_names().join(", ")
+            ));
+            for row in 0..(1_usize << in_names.len()) {
+                let mut line: String = (0..in_names.len())
+                    .map(|i| bit(row & (1 << (in_names.len() - 1 - i)) != 0))
+                    .collect();
+                line.push(' ');
+                line.extend(table.iter().map(|out| bit(out[row])));
+                code.push_str(&line);
+                code.push('\n');
+            }
+            code.push_str("}\n");
+        }
+
+        for test in self.tests.iter() {
+            code.push_str(&format!(
+                "\ntest {{\n{} -> {};\n",
+                test.in_names.join(" "),
+                test.out_names.join(" ")
+            ));
+            for vector in test.vectors.iter() {
+                let inputs: Vec<String> = vector
+                    .inputs
+                    .iter()
+                    .map(|level| level.input_char().to_string())
+                    .collect();
+                let outputs: Vec<String> = vector
+                    .outputs
+                    .iter()
+                    .map(|level| level.output_char().to_string())
+                    .collect();
+                code.push_str(&format!("{} -> {};\n", inputs.join(" "), outputs.join(" ")));
+            }
+            code.push_str("}\n");
+        }
+
+        for assertion in self.assertions.iter() {
+            code.push_str(&match assertion.property() {
+                Property::Invariant => format!("\nassert {};", assertion.source()),
+                Property::Always(None) => format!("\nassert always {};", assertion.source()),
+                Property::Always(Some(bound)) => {
+                    format!("\nassert always {} within {};", assertion.source(), bound)
+                }
+                Property::Eventually(bound) => {
+                    format!(
+                        "\nassert eventually {} within {};",
+                        assertion.source(),
+                        bound
+                    )
+                }
+            });
+        }
+
+        format_code(&code).expect("printed source can always be lexed")
+    }
+}
+
+/// the single product term a control table is made of, the first input is the highest bit
+fn product_term(lut: &LookupTable) -> String {
+    let in_names = lut.in_names();
+    let (value, mask) = match prime_implicants(&lut.get_table()[0]).first() {
+        Some(&cube) => cube,
+        None => return "0".to_string(),
+    };
+    let literals: Vec<String> = in_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name, 1 << (in_names.len() - 1 - i)))
+        .filter(|&(_, bit)| mask & bit == 0)
+        .map(|(name, bit)| {
+            if value & bit != 0 {
+                name.clone()
+            } else {
+                format!("!{}", name)
+            }
+        })
+        .collect();
+    if literals.is_empty() {
+        "1".to_string()
+    } else {
+        literals.join(" & ")
+    }
+}
+
+fn bit(val: bool) -> char {
+    if val {
+        '1'
+    } else {
+        '0'
+    }
+}
+
+/// a token or a comment (`token` is `None`) with the number of line breaks in front of it
+struct Item {
+    token: Option<Token>,
+    text: String,
+    newlines: usize,
+}
+
+fn items(code: &str) -> Result<Vec<Item>, String> {
+    let mut result = Vec::new();
+    let mut newlines = 0;
+
+    for (token, span) in Token::lexer(code).spanned() {
+        let text = &code[span.clone()];
+        match token {
+            Token::Unknown => {
+                return Err(format!("unexpected {:?} at byte {}", text, span.start));
+            }
+            Token::Ignore(_) if text.starts_with("//") => {
+                result.push(Item {
+                    token: None,
+                    text: text.trim_end().to_string(),
+                    newlines,
+                });
+                newlines = text.ends_with('\n') as usize;
+            }
+            Token::Ignore(_) if text.starts_with("/*") => {
+                result.push(Item {
+                    token: None,
+                    text: text.to_string(),
+                    newlines,
+                });
+                newlines = 0;
+            }
+            Token::Ignore(_) => newlines += text.matches('\n').count(),
+            token => {
+                result.push(Item {
+                    token: Some(token),
+                    text: text.trim_end().to_string(),
+                    newlines,
+                });
+                newlines = 0;
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+#[derive(Default)]
+struct Formatter {
+    lines: Vec<String>,
+    /// the statement which is currently written
+    line: String,
+    prev: Option<Token>,
+    /// the first token of the current statement
+    first: Option<Token>,
+    /// the keyword of the `table` or `test` block whose rows are collected
+    block: Option<Token>,
+    rows: Vec<Row>,
+}
+
+#[derive(Default)]
+struct Row {
+    text: String,
+    comment: Option<String>,
+    prev: Option<Token>,
+    done: bool,
+}
+
+impl Formatter {
+    fn push(&mut self, token: &Token, text: &str, newlines: usize) {
+        if self.block.is_some() {
+            return self.push_row(token, text, newlines);
+        }
+
+        if let Token::Pragma(_) = token {
+            self.end_line();
+            self.blank_line(newlines);
+            self.lines.push(text.to_string());
+            return;
+        }
+
+        if self.line.is_empty() {
+            self.blank_line(newlines);
+            self.first = Some(token.clone());
+        }
+
+        if *token == Token::CurlyOpen {
+            self.line.push_str(" {");
+            self.block = self.first.clone();
+            self.end_line();
+            return;
+        }
+
+        append(&mut self.line, &self.prev, token, text);
+        self.prev = Some(token.clone());
+        if *token == Token::Semicolon {
+            self.end_line();
+        }
+    }
+
+    fn push_row(&mut self, token: &Token, text: &str, newlines: usize) {
+        if *token == Token::CurlyClose {
+            self.end_block();
+            return;
+        }
+
+        // table rows end at a line break and test vectors at a semicolon
+        let table = self.block == Some(Token::Table);
+        let new_row = match self.rows.last() {
+            None => true,
+            Some(row) => row.done || (table && newlines > 0),
+        };
+        if new_row {
+            self.rows.push(Row::default());
+        }
+
+        let row = self.rows.last_mut().unwrap();
+        append(&mut row.text, &row.prev, token, text);
+        row.prev = Some(token.clone());
+        row.done = !table && *token == Token::Semicolon;
+    }
+
+    fn comment(&mut self, text: &str, newlines: usize) {
+        if self.block.is_some() {
+            match self.rows.last_mut() {
+                Some(row) if newlines == 0 && row.comment.is_none() => {
+                    row.comment = Some(text.to_string());
+                    row.done = true;
+                }
+                _ => self.rows.push(Row {
+                    comment: Some(text.to_string()),
+                    done: true,
+                    ..Row::default()
+                }),
+            }
+            return;
+        }
+
+        let own_line = newlines > 0 || self.lines.is_empty();
+        if self.line.is_empty() {
+            if own_line {
+                self.blank_line(newlines);
+                self.lines.push(text.to_string());
+            } else {
+                let last = self.lines.last_mut().unwrap();
+                last.push(' ');
+                last.push_str(text);
+            }
+        } else {
+            // a comment in the middle of a statement, the rest continues on the next line
+            self.line.push(' ');
+            self.line.push_str(text);
+            if text.starts_with("//") {
+                let first = self.first.clone();
+                self.end_line();
+                self.line.push_str("    ");
+                self.first = first;
+            }
+        }
+    }
+
+    fn blank_line(&mut self, newlines: usize) {
+        if newlines > 1 && self.lines.last().is_some_and(|line| !line.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+
+    fn end_line(&mut self) {
+        if !self.line.trim().is_empty() {
+            self.lines.push(self.line.clone());
+        }
+        self.line.clear();
+        self.prev = None;
+    }
+
+    /// aligns the columns of all rows and closes the block
+    fn end_block(&mut self) {
+        let rows: Vec<Row> = self.rows.drain(..).collect();
+        let cells: Vec<Vec<&str>> = rows
+            .iter()
+            .map(|row| {
+                row.text
+                    .split(' ')
+                    .filter(|cell| !cell.is_empty())
+                    .collect()
+            })
+            .collect();
+
+        let mut widths = Vec::new();
+        for row in cells.iter() {
+            for (i, cell) in row.iter().enumerate() {
+                if widths.len() <= i {
+                    widths.push(0);
+                }
+                widths[i] = widths[i].max(cell.len());
+            }
+        }
+
+        for (row, cells) in rows.iter().zip(cells.iter()) {
+            let mut line = String::from("    ");
+            for (cell, width) in cells.iter().zip(widths.iter()) {
+                line.push_str(&format!("{:<width$} ", cell, width = width));
+            }
+            let mut line = line.trim_end().to_string();
+            if let Some(comment) = &row.comment {
+                if !cells.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(comment);
+            }
+            self.lines.push(line);
+        }
+
+        self.lines.push("}".to_string());
+        self.block = None;
+        self.first = None;
+    }
+
+    fn finish(mut self) -> String {
+        if self.block.is_some() {
+            self.end_block();
+        }
+        self.end_line();
+        while self.lines.last().is_some_and(|line| line.is_empty()) {
+            self.lines.pop();
+        }
+
+        let mut result = self.lines.join("\n");
+        result.push('\n');
+        result
+    }
+}
+
+/// appends a token with the spacing of the canonical style
+fn append(line: &mut String, prev: &Option<Token>, token: &Token, text: &str) {
+    let space = match (prev, token) {
+        (Some(Token::Identifier(word)), Token::RoundOpen) => {
+            !["rom", "latch", "helper"].contains(&word.as_str())
+        }
+        _ => !matches!(
+            (prev, token),
+            (None, _)
+                | (_, Token::Comma)
+                | (_, Token::Semicolon)
+                | (_, Token::RoundClose)
+                | (_, Token::SquareClose)
+                | (_, Token::Dot)
+                | (Some(Token::RoundOpen), _)
+                | (Some(Token::SquareOpen), _)
+                | (Some(Token::Dot), _)
+                | (Some(Token::Not), _)
+                | (Some(Token::Identifier(_)), Token::SquareOpen)
+                | (Some(Token::Table), Token::RoundOpen)
+                | (Some(Token::Fill), Token::RoundOpen)
+        ),
+    };
+
+    if space && !line.ends_with(' ') {
+        line.push(' ');
+    }
+    line.push_str(text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_code;
+
+    #[test]
+    fn spacing() {
+        assert_eq!(
+            format_code("pin [1..2]=a,b;y=!a&(b|c);x.dff;"),
+            Ok("pin [1..2] = a, b;\ny = !a & (b | c);\nx.dff;\n".to_string())
+        );
+        assert_eq!(
+            format_code("assert always(s!=0b11)within 2;"),
+            Ok("assert always (s != 0b11) within 2;\n".to_string())
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use super::OGal;
+use hardware_sim::Component;
+use std::collections::HashMap;
+
+impl OGal {
+    /// Every signal driven by a table and the signals it is computed from. Registered outputs
+    /// are left out because the flip flop breaks the path.
+    pub fn dependencies(&self) -> Vec<(String, Vec<String>)> {
+        let mut result = Vec::new();
+        for lut in self.lut.iter() {
+            for out_name in lut.out_names() {
+                if !self.dff.contains(&out_name) {
+                    result.push((out_name, lut.in_names()));
+                }
+            }
+        }
+        result
+    }
+
+    /// Finds combinational feedback e.g. `a = b & c; b = a | d;`. Each loop is returned as the
+    /// path of signal names starting and ending with the same name, `a -> b -> a`.
+    pub fn combinational_loops(&self) -> Vec<Vec<String>> {
+        let graph = self.dependencies();
+        let index: HashMap<&str, usize> = graph
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.as_str(), i))
+            .collect();
+        let edges: Vec<Vec<usize>> = graph
+            .iter()
+            .map(|(_, in_names)| {
+                in_names
+                    .iter()
+                    .filter_map(|name| index.get(name.as_str()).cloned())
+                    .collect()
+            })
+            .collect();
+
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            OnStack,
+            Done,
+        }
+
+        fn visit(
+            node: usize,
+            edges: &[Vec<usize>],
+            state: &mut [State],
+            stack: &mut Vec<usize>,
+            loops: &mut Vec<Vec<usize>>,
+        ) {
+            state[node] = State::OnStack;
+            stack.push(node);
+            for &next in edges[node].iter() {
+                match state[next] {
+                    State::New => visit(next, edges, state, stack, loops),
+                    State::OnStack => {
+                        let start = stack.iter().position(|&n| n == next).unwrap();
+                        let mut path = stack[start..].to_vec();
+                        path.push(next);
+                        loops.push(path);
+                    }
+                    State::Done => (),
+                }
+            }
+            stack.pop();
+            state[node] = State::Done;
+        }
+
+        let mut state = vec![State::New; graph.len()];
+        let mut loops = Vec::new();
+        for node in 0..graph.len() {
+            if state[node] == State::New {
+                visit(node, &edges, &mut state, &mut Vec::new(), &mut loops);
+            }
+        }
+
+        // the same loop is found once per back edge into it, keep one path for every set of names
+        let mut seen = Vec::new();
+        let mut result = Vec::new();
+        for path in loops {
+            let mut key = path[1..].to_vec();
+            key.sort_unstable();
+            if !seen.contains(&key) {
+                seen.push(key);
+                result.push(path.iter().map(|&n| graph[n].0.clone()).collect());
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OGal;
+
+    #[test]
+    fn combinational_loops() {
+        let o_gal = OGal::parse("a = b & c; b = a | d; x = x & e; y = a;").unwrap();
+        assert_eq!(
+            o_gal.combinational_loops(),
+            vec![vec!["a", "b", "a"], vec!["x", "x"]]
+        );
+
+        let o_gal = OGal::parse("a = b & c; b = a | d; b.dff;").unwrap();
+        assert!(o_gal.combinational_loops().is_empty());
+    }
+}
@@ -0,0 +1,477 @@
+//! Lowers the `Ast` into the lookup tables and attributes of an `OGal`.
+
+use super::ast::{self, Ast, ExprKind, Name, Span, Statement, TableMode};
+use super::{rom, OGal, ParserOptions};
+use crate::assertion::Assertion;
+use crate::test_vector::{Level, TestBlock, TestVector};
+use crate::{Diagnostic, Header};
+use hardware_sim::{Component, LookupTable};
+use std::collections::HashMap;
+use std::path::Path;
+
+impl OGal {
+    /// Builds the design from a parsed `Ast`, `options` is the dialect the `Ast` was parsed with
+    /// and changes with the `#pragma` lines like it did while parsing.
+    pub fn from_ast(ast: &Ast, mut options: ParserOptions) -> Result<Self, Diagnostic> {
+        let mut o_gal = OGal::new(Vec::new(), Vec::new(), Vec::new());
+        // the first statement which drives a name, for the note of a second driver
+        let mut driven: HashMap<String, Span> = HashMap::new();
+
+        for statement in ast.statements.iter() {
+            match statement {
+                Statement::Pin(pin) => {
+                    for (name, &num) in pin.names.iter().zip(pin.numbers.iter()) {
+                        if let Some(old) = o_gal.pins.insert(name.name.clone(), num) {
+                            return Err(error(
+                                format!("pin {} has been already assigned to {}", name.name, old),
+                                name.span,
+                            ));
+                        }
+                    }
+                }
+                Statement::Node(node) => {
+                    for name in node.names.iter() {
+                        if o_gal.nodes.contains(&name.name) {
+                            return Err(error(
+                                format!("{} is already a node", name.name),
+                                name.span,
+                            ));
+                        }
+                        if node.dff {
+                            o_gal.dff.push(name.name.clone());
+                        }
+                        o_gal.nodes.push(name.name.clone());
+                    }
+                }
+                Statement::Assignment(assignment) => {
+                    let names = names(&assignment.targets);
+                    if let Some(gate) = &assignment.gate {
+                        drivers(&mut driven, &assignment.targets, false)?;
+                        o_gal.lut.push(latch_macro(
+                            &assignment.targets,
+                            gate,
+                            &assignment.expression,
+                        )?);
+                        o_gal.latch.extend(names);
+                        continue;
+                    }
+
+                    drivers(&mut driven, &assignment.targets, assignment.merge)?;
+                    let (func, _) = expression(&assignment.expression);
+                    let new = expression_lut(&func, &names, assignment.expression.span)?;
+                    if !assignment.merge {
+                        o_gal.lut.push(new);
+                        continue;
+                    }
+                    for name in names.iter() {
+                        // `drivers` made sure an earlier table drives it
+                        let i = o_gal
+                            .lut
+                            .iter()
+                            .position(|lut| lut.out_names().contains(name))
+                            .unwrap();
+                        o_gal.lut[i] =
+                            merge_or(&o_gal.lut[i], name, &new.in_names(), &new.get_table()[0]);
+                    }
+                }
+                Statement::Table(table) => {
+                    drivers(&mut driven, &table.outputs, false)?;
+                    o_gal.lut.push(table_lut(table, &options)?);
+                }
+                Statement::Rom(decl) => {
+                    drivers(&mut driven, &decl.outputs, false)?;
+                    o_gal.lut.push(rom_lut(decl, &options)?);
+                }
+                Statement::Dff(dff) => o_gal.dff.extend(names(&dff.names)),
+                Statement::Latch(latch) => o_gal.latch.extend(names(&latch.names)),
+                Statement::HazardFree(decl) => o_gal.hazard_free.extend(names(&decl.names)),
+                Statement::Helper(helper) => {
+                    for name in helper.names.iter() {
+                        o_gal.helper.push((name.name.clone(), helper.pins.clone()));
+                    }
+                }
+                Statement::Control(decl) => {
+                    let (func, _) = expression(&decl.expression);
+                    for name in decl.names.iter() {
+                        if o_gal
+                            .controls
+                            .iter()
+                            .any(|(n, c, _)| *n == name.name && *c == decl.control)
+                        {
+                            return Err(error(
+                                format!(
+                                    "{}.{} has been already set",
+                                    name.name,
+                                    decl.control.name()
+                                ),
+                                name.span,
+                            ));
+                        }
+                        let names = std::slice::from_ref(&name.name);
+                        let lut = expression_lut(&func, names, decl.expression.span)?;
+                        o_gal.controls.push((name.name.clone(), decl.control, lut));
+                    }
+                }
+                Statement::Test(test) => o_gal.tests.push(test_block(test)?),
+                Statement::Assert(assert) => {
+                    let (func, source) = expression(&assert.expression);
+                    let names = [String::from("assert")];
+                    let lut = expression_lut(&func, &names, assert.expression.span)?;
+                    o_gal
+                        .assertions
+                        .push(Assertion::new(lut, source, assert.property));
+                }
+                Statement::Header(field) => header_field(&mut o_gal.header, field)?,
+                Statement::Pragma(pragma) => {
+                    if let Err(msg) = options.pragma(&pragma.text) {
+                        return Err(error(msg, pragma.span));
+                    }
+                }
+                Statement::Comment(_) => (),
+            }
+        }
+
+        Ok(o_gal)
+    }
+}
+
+fn error(msg: String, span: Span) -> Diagnostic {
+    Diagnostic::error(&msg, Some(span))
+}
+
+fn names(names: &[Name]) -> Vec<String> {
+    names.iter().map(|name| name.name.clone()).collect()
+}
+
+/// Every output may only be driven by one statement, otherwise `ogal2td` would produce two tables
+/// for the same pin. More terms can be added with `y |= expr;`.
+fn drivers(
+    driven: &mut HashMap<String, Span>,
+    names: &[Name],
+    merge: bool,
+) -> Result<(), Diagnostic> {
+    for name in names {
+        match (driven.get(&name.name), merge) {
+            (None, false) => {
+                driven.insert(name.name.clone(), name.span);
+            }
+            (Some(_), true) => (),
+            (None, true) => {
+                return Err(error(
+                    format!(
+                        "{} |= needs an earlier statement driving {}",
+                        name.name, name.name
+                    ),
+                    name.span,
+                ))
+            }
+            (Some(&first), false) => {
+                let mut diagnostic = error(
+                    format!(
+                        "{} is driven by more than one statement, use `{} |= ...` to or them",
+                        name.name, name.name
+                    ),
+                    name.span,
+                );
+                diagnostic
+                    .related
+                    .push((first, "first driven here".to_string()));
+                return Err(diagnostic);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn header_field(header: &mut Header, field: &ast::HeaderField) -> Result<(), Diagnostic> {
+    let value = match field.key.as_str() {
+        "name" => &mut header.name,
+        "partno" => &mut header.partno,
+        "revision" => &mut header.revision,
+        "designer" => &mut header.designer,
+        "company" => &mut header.company,
+        "date" => &mut header.date,
+        "device" => &mut header.device,
+        key => return Err(error(format!("unknown header field {}", key), field.span)),
+    };
+    if value.is_some() {
+        return Err(error(
+            format!("{} has been already set", field.key),
+            field.span,
+        ));
+    }
+    *value = Some(field.value.clone());
+    Ok(())
+}
+
+/// Returns the tokens of the expression and its source text, comparisons like `state != 0b11`
+/// are expanded into the single bits `state1` and `state0`.
+fn expression(expression: &ast::Expression) -> (Vec<bool_algebra::Token>, String) {
+    let mut func = Vec::new();
+    let mut source = String::new();
+    for token in expression.tokens.iter() {
+        let bool_token = match &token.kind {
+            ExprKind::And => bool_algebra::Token::And,
+            ExprKind::Or => bool_algebra::Token::Or,
+            ExprKind::Xor => bool_algebra::Token::Xor,
+            ExprKind::Not => bool_algebra::Token::Not,
+            ExprKind::Open => bool_algebra::Token::Open,
+            ExprKind::Close => bool_algebra::Token::Close,
+            ExprKind::Const(false) => bool_algebra::Token::Zero,
+            ExprKind::Const(true) => bool_algebra::Token::One,
+            ExprKind::Var(name) => bool_algebra::Token::Var(name.name.clone()),
+            ExprKind::Compare { name, negate, bits } => {
+                source.push_str(&format!(
+                    "{} {} 0b{}",
+                    name.name,
+                    if *negate { "!=" } else { "==" },
+                    bits
+                ));
+                func.append(&mut comparison(&name.name, *negate, bits));
+                continue;
+            }
+        };
+
+        source.push_str(&match &bool_token {
+            bool_algebra::Token::And => " & ".to_string(),
+            bool_algebra::Token::Or => " | ".to_string(),
+            bool_algebra::Token::Xor => " ^ ".to_string(),
+            bool_algebra::Token::Not => "!".to_string(),
+            bool_algebra::Token::Open => "(".to_string(),
+            bool_algebra::Token::Close => ")".to_string(),
+            bool_algebra::Token::Zero => "0".to_string(),
+            bool_algebra::Token::One => "1".to_string(),
+            bool_algebra::Token::Var(name) => name.clone(),
+        });
+        func.push(bool_token);
+    }
+    (func, source)
+}
+
+/// expands `name == 0b10` into `(name1 & !name0)` and `!=` into its negation
+fn comparison(name: &str, negate: bool, bits: &str) -> Vec<bool_algebra::Token> {
+    let mut result = Vec::new();
+    if negate {
+        result.push(bool_algebra::Token::Not);
+    }
+    result.push(bool_algebra::Token::Open);
+    for (i, bit) in bits.chars().enumerate() {
+        if i != 0 {
+            result.push(bool_algebra::Token::And);
+        }
+        if bit == '0' {
+            result.push(bool_algebra::Token::Not);
+        }
+        result.push(bool_algebra::Token::Var(format!(
+            "{}{}",
+            name,
+            bits.len() - 1 - i
+        )));
+    }
+    result.push(bool_algebra::Token::Close);
+    result
+}
+
+/// builds the lookup table of an expression with the same table for every output name
+fn expression_lut(
+    func: &Vec<bool_algebra::Token>,
+    names: &[String],
+    span: Span,
+) -> Result<LookupTable, Diagnostic> {
+    let out_table = match bool_algebra::parse(func) {
+        Ok(table) => table,
+        Err(msg) => return Err(error(msg, span)),
+    };
+
+    let table = vec![out_table; names.len()];
+    let in_names = bool_algebra::get_names(func);
+    let in_names = in_names.iter().map(|s| s.as_ref()).collect();
+    let out_names = names.iter().map(|s| s.as_ref()).collect();
+    Ok(LookupTable::new(table, in_names, out_names, "").unwrap())
+}
+
+/// `q.latch(g) = d;` is `q = d & g | q & !g | d & q`, the last term is the consensus of the other
+/// two and keeps `q` from glitching when `g` falls while `d` is 1
+fn latch_macro(
+    targets: &[Name],
+    gate: &ast::Expression,
+    data: &ast::Expression,
+) -> Result<LookupTable, Diagnostic> {
+    let names = names(targets);
+    if names.len() != 1 {
+        return Err(error(
+            format!(
+                "a latch needs exactly one name but got {}",
+                names.join(", ")
+            ),
+            targets[0].span.to(targets[targets.len() - 1].span),
+        ));
+    }
+    let (gate, _) = expression(gate);
+    let (data_func, _) = expression(data);
+
+    use bool_algebra::Token::{And, Close, Not, Open, Or, Var};
+    let group = |func: &[bool_algebra::Token]| {
+        let mut result = vec![Open];
+        result.extend(func.iter().cloned());
+        result.push(Close);
+        result
+    };
+    let q = Var(names[0].clone());
+
+    let mut func = group(&data_func);
+    func.push(And);
+    func.append(&mut group(&gate));
+    func.extend(vec![Or, q.clone(), And, Not]);
+    func.append(&mut group(&gate));
+    func.push(Or);
+    func.append(&mut group(&data_func));
+    func.extend(vec![And, q]);
+
+    expression_lut(&func, &names, data.span)
+}
+
+/// ors `table` into the output `name` of `lut`, the inputs of the result are the union of both
+fn merge_or(lut: &LookupTable, name: &str, in_names: &[String], table: &[bool]) -> LookupTable {
+    let old_names = lut.in_names();
+    let mut union = old_names.clone();
+    for in_name in in_names {
+        if !union.contains(in_name) {
+            union.push(in_name.clone());
+        }
+    }
+
+    // the row of a table over `names` which matches `row` of the table over `union`
+    let index = |names: &[String], row: usize| {
+        let mut result = 0;
+        for (i, in_name) in names.iter().enumerate() {
+            let pos = union.iter().position(|n| n == in_name).unwrap();
+            if row & (1 << (union.len() - 1 - pos)) != 0 {
+                result |= 1 << (names.len() - 1 - i);
+            }
+        }
+        result
+    };
+
+    let mut tables = Vec::new();
+    for (out, column) in lut.out_names().iter().zip(lut.get_table().iter()) {
+        let mut result = Vec::with_capacity(1 << union.len());
+        for row in 0..(1 << union.len()) {
+            let mut val = column[index(&old_names, row)];
+            if out == name {
+                val |= table[index(in_names, row)];
+            }
+            result.push(val);
+        }
+        tables.push(result);
+    }
+
+    let out_names = lut.out_names();
+    let in_names = union.iter().map(|s| s.as_ref()).collect();
+    let out_names = out_names.iter().map(|s| s.as_ref()).collect();
+    LookupTable::new(tables, in_names, out_names, "").unwrap()
+}
+
+fn table_lut(table: &ast::TableDecl, options: &ParserOptions) -> Result<LookupTable, Diagnostic> {
+    let bits: Vec<bool> = table
+        .rows
+        .iter()
+        .flat_map(|row| row.bits.chars().map(|c| c == '1'))
+        .collect();
+    let (num_in, num_out) = (table.inputs.len(), table.outputs.len());
+    let result = match table.mode {
+        TableMode::Count => {
+            bool_algebra::parse_count(num_in, num_out, bits, options.count_vertical)
+        }
+        TableMode::Fill(fill) => bool_algebra::parse_fill(num_in, num_out, bits, fill),
+        TableMode::Full => bool_algebra::parse_full(num_in, num_out, bits),
+    };
+    let result = match result {
+        Ok(result) => result,
+        Err(msg) => return Err(error(msg, table.span)),
+    };
+
+    let in_names = table.inputs.iter().map(|name| name.name.as_ref()).collect();
+    let out_names = table
+        .outputs
+        .iter()
+        .map(|name| name.name.as_ref())
+        .collect();
+    Ok(LookupTable::new(result, in_names, out_names, "").unwrap())
+}
+
+/// `rom(addr[0..4] -> d[0..3]) from "table.hex";` the first address name is bit 0 of the address
+/// and the first data name bit 0 of the word
+fn rom_lut(decl: &ast::RomDecl, options: &ParserOptions) -> Result<LookupTable, Diagnostic> {
+    let path = match &options.rom_dir {
+        Some(dir) => Path::new(dir).join(&decl.file),
+        None => Path::new(&decl.file).to_path_buf(),
+    };
+    let (num_in, num_out) = (decl.inputs.len(), decl.outputs.len());
+    let words = match rom::load(&path, num_in, num_out) {
+        Ok(words) => words,
+        Err(msg) => return Err(error(msg, decl.span)),
+    };
+
+    let mut table = vec![Vec::with_capacity(words.len()); num_out];
+    for row in 0..words.len() {
+        let mut address = 0;
+        for i in 0..num_in {
+            if row & (1 << (num_in - 1 - i)) != 0 {
+                address |= 1 << i;
+            }
+        }
+        for (i, out) in table.iter_mut().enumerate() {
+            out.push(words[address] & (1 << i) != 0);
+        }
+    }
+
+    let in_names = decl.inputs.iter().map(|name| name.name.as_ref()).collect();
+    let out_names = decl.outputs.iter().map(|name| name.name.as_ref()).collect();
+    Ok(LookupTable::new(table, in_names, out_names, "").unwrap())
+}
+
+fn test_block(test: &ast::TestDecl) -> Result<TestBlock, Diagnostic> {
+    let in_names = names(&test.inputs);
+    let out_names = names(&test.outputs);
+
+    let mut vectors = Vec::new();
+    for vector in test.vectors.iter() {
+        let inputs = levels(&vector.inputs, Level::from_input, vector.span)?;
+        let outputs = levels(&vector.outputs, Level::from_output, vector.span)?;
+
+        if inputs.len() != in_names.len() || outputs.len() != out_names.len() {
+            return Err(error(
+                format!(
+                    "test vector {} has {} inputs and {} outputs but {} inputs and {} outputs are declared",
+                    vectors.len() + 1,
+                    inputs.len(),
+                    outputs.len(),
+                    in_names.len(),
+                    out_names.len()
+                ),
+                vector.span,
+            ));
+        }
+
+        vectors.push(TestVector { inputs, outputs });
+    }
+
+    Ok(TestBlock {
+        in_names,
+        out_names,
+        vectors,
+    })
+}
+
+/// the levels of the characters e.g. `01C` or `HLZ`
+fn levels(
+    chars: &str,
+    level: fn(char) -> Result<Level, String>,
+    span: Span,
+) -> Result<Vec<Level>, Diagnostic> {
+    chars
+        .chars()
+        .map(|c| level(c).map_err(|msg| error(msg, span)))
+        .collect()
+}
@@ -1,20 +1,63 @@
+pub mod ast;
+mod fitter;
+mod format;
+mod graph;
+mod lower;
+mod options;
+mod report;
+mod rom;
 mod test;
 
-use crate::TableData;
+pub use fitter::write_pins;
+pub use format::format_code;
+pub use options::ParserOptions;
+pub use report::Hazard;
+
+use crate::assertion::Assertion;
+use crate::test_vector::TestBlock;
+use crate::{Control, ControlTable, Diagnostic, Header, Severity, TableData};
+use ast::Ast;
 use hardware_sim::LookupTable;
-use logos::Logos;
 use std::collections::HashMap;
-use tokenizer::{Error, Tokenizer, TypeEq};
+use tokenizer::{Error, TypeEq};
 
-pub fn parse(code: &str) -> Result<Vec<TableData>, Error> {
-    let o_gal = OGal::parse(code)?;
-    ogal2td(o_gal)
+pub fn parse(code: &str, options: ParserOptions) -> Result<(Vec<TableData>, Header), Error> {
+    let ast = match Ast::parse_with(code, options.clone()) {
+        Ok(ast) => ast,
+        Err(err) => return Err(to_error(code, &err.into())),
+    };
+    let o_gal = match OGal::from_ast(&ast, options) {
+        Ok(o_gal) => o_gal,
+        Err(diagnostic) => return Err(to_error(code, &diagnostic)),
+    };
+    if let Err(msg) = o_gal.verify() {
+        return Err(Error::msg(&msg));
+    }
+    let diagnostics = crate::lint::lints(&ast, &o_gal);
+    if let Some(denied) = diagnostics.iter().find(|d| d.severity == Severity::Error) {
+        return Err(Error::msg(&format!(
+            "{}: {}",
+            denied.code.clone().unwrap_or_default(),
+            denied.msg
+        )));
+    }
+    let header = o_gal.header.clone();
+    ogal2td(o_gal).map(|td_vec| (td_vec, header))
 }
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct OGal {
-    pins: HashMap<String, usize>,
-    lut: Vec<LookupTable>,
-    dff: Vec<String>,
+    pub(crate) pins: HashMap<String, usize>,
+    pub(crate) lut: Vec<LookupTable>,
+    pub(crate) dff: Vec<String>,
+    pub(crate) latch: Vec<String>,
+    pub(crate) hazard_free: Vec<String>,
+    pub(crate) helper: Vec<(String, Vec<usize>)>,
+    pub(crate) controls: Vec<(String, Control, LookupTable)>,
+    pub(crate) nodes: Vec<String>,
+    header: Header,
+    tests: Vec<TestBlock>,
+    assertions: Vec<Assertion>,
 }
 
 impl OGal {
@@ -28,333 +71,123 @@ impl OGal {
             pins: pin_map,
             lut,
             dff: dff.iter().map(|&s| s.to_string()).collect(),
+            latch: Vec::new(),
+            hazard_free: Vec::new(),
+            helper: Vec::new(),
+            controls: Vec::new(),
+            nodes: Vec::new(),
+            header: Header::new(),
+            tests: Vec::new(),
+            assertions: Vec::new(),
         }
     }
 
-    pub fn parse(code: &str) -> Result<Self, Error> {
-        let mut pins = HashMap::new();
-        let mut lut = Vec::new();
-        let mut dff = Vec::new();
-
-        let mut tokenizer = Tokenizer::new(Token::lexer(code), vec![Token::Ignore((0, None))]);
-
-        while let Some(token) = tokenizer.peek() {
-            match token {
-                Token::Pin => {
-                    pin(&mut tokenizer, &mut pins)?;
-                }
-                Token::Table => table(&mut tokenizer, &mut lut)?,
-                Token::Identifier(_) => {
-                    let names = pin_name(&mut tokenizer)?;
-                    if tokenizer.next_is(Token::Dot) {
-                        get_dff(names, &mut tokenizer, &mut dff)?;
-                    } else {
-                        func(names, &mut tokenizer, &mut lut)?;
-                    }
-                }
-                _ => {
-                    tokenizer.next();
-                    tokenizer.expect_multi(vec![
-                        Token::Pin,
-                        Token::Table,
-                        Token::Identifier(String::new()),
-                    ])?;
-                    unreachable!();
-                }
-            }
-        }
-
-        Ok(Self { pins, lut, dff })
+    pub fn pins(&self) -> &HashMap<String, usize> {
+        &self.pins
     }
-}
 
-fn pin(tokenizer: &mut Tokenizer<Token>, pins: &mut HashMap<String, usize>) -> Result<(), Error> {
-    tokenizer.expect_next(Token::Pin)?;
-    let nums = pin_num(tokenizer)?;
-    tokenizer.expect_next(Token::Equals)?;
-    let names = pin_name(tokenizer)?;
-
-    if nums.len() != names.len() {
-        return Err(tokenizer.error(&format!(
-            "pin name len ({}) and pin number len ({}) doesn't match",
-            names.len(),
-            nums.len()
-        )));
+    /// one table per assignment, `table` and `rom` in the order they appear in the source
+    pub fn luts(&self) -> &Vec<LookupTable> {
+        &self.lut
     }
 
-    for i in 0..nums.len() {
-        if let Some(num) = pins.insert(names[i].clone(), nums[i]) {
-            return Err(tokenizer.error(&format!(
-                "pin {} has been already assigned to {}",
-                names[i].clone(),
-                num
-            )));
-        }
+    pub fn dffs(&self) -> &Vec<String> {
+        &self.dff
     }
 
-    tokenizer.expect_next(Token::Semicolon)?;
-
-    Ok(())
-}
-
-fn func(
-    names: Vec<String>,
-    tokenizer: &mut Tokenizer<Token>,
-    lut: &mut Vec<LookupTable>,
-) -> Result<(), Error> {
-    tokenizer.expect_next(Token::Equals)?;
-
-    let mut func = Vec::new();
-    while let Some(token) = tokenizer.next() {
-        let bool_token = match token {
-            Token::And => bool_algebra::Token::And,
-            Token::Or => bool_algebra::Token::Or,
-            Token::Xor => bool_algebra::Token::Xor,
-            Token::Not => bool_algebra::Token::Not,
-            Token::Identifier(name) => bool_algebra::Token::Var(name),
-            Token::RoundClose => bool_algebra::Token::Close,
-            Token::RoundOpen => bool_algebra::Token::Open,
-            Token::Number(num) => {
-                if num == "0" {
-                    bool_algebra::Token::Zero
-                } else if num == "1" {
-                    bool_algebra::Token::One
-                } else {
-                    return Err(tokenizer
-                        .error(&format!("unexpected char {} only '0' or '1' allowed", num)));
-                }
-            }
-            Token::Semicolon => break,
-            _ => {
-                tokenizer.expect(Token::Semicolon)?;
-                unreachable!();
-            }
-        };
-
-        func.push(bool_token);
+    /// the names declared with `.latch` or driven by the latch macro, combinational loops through
+    /// them are intended and they get every prime implicant so they don't glitch
+    pub fn latches(&self) -> &Vec<String> {
+        &self.latch
     }
 
-    let out_table = match bool_algebra::parse(&func) {
-        Ok(table) => table,
-        Err(msg) => return Err(tokenizer.error(&msg)),
-    };
-
-    let mut table = Vec::new();
-    for _ in 0..names.len() {
-        table.push(out_table.clone());
+    /// the names declared with `.hazard_free`, they get consensus terms so a single changing
+    /// input can't glitch them
+    pub fn hazard_free(&self) -> &Vec<String> {
+        &self.hazard_free
     }
-    let in_names = bool_algebra::get_names(&func);
-    let in_names = in_names.iter().map(|s| s.as_ref()).collect();
-    let out_names = names.iter().map(|s| s.as_ref()).collect();
-    let lt = LookupTable::new(table, in_names, out_names, "").unwrap();
-
-    lut.push(lt);
-
-    Ok(())
-}
 
-fn get_dff(
-    names: Vec<String>,
-    tokenizer: &mut Tokenizer<Token>,
-    dff: &mut Vec<String>,
-) -> Result<(), Error> {
-    tokenizer.expect_next(Token::Dot)?;
-    tokenizer.expect_next(Token::Dff)?;
-    tokenizer.expect_next(Token::Semicolon)?;
-
-    for name in names {
-        dff.push(name);
+    /// the OLMCs locked with `y.helper(20);` for the terms of `y` which don't fit into its own
+    pub fn helpers(&self) -> &Vec<(String, Vec<usize>)> {
+        &self.helper
     }
 
-    Ok(())
-}
-
-fn table(tokenizer: &mut Tokenizer<Token>, lut: &mut Vec<LookupTable>) -> Result<(), Error> {
-    tokenizer.expect_next(Token::Table)?;
-    tokenizer.expect_next(Token::RoundOpen)?;
-    let in_names = pin_name(tokenizer)?;
-    tokenizer.expect_next(Token::Arrow)?;
-    let out_names = pin_name(tokenizer)?;
-    tokenizer.expect_next(Token::RoundClose)?;
-
-    enum Fill {
-        Fill(bool),
-        None,
-        Count,
+    /// the product terms of `y.oe = expr;` with the table of `expr`
+    pub fn controls(&self) -> &Vec<(String, Control, LookupTable)> {
+        &self.controls
     }
 
-    let fill;
-
-    if tokenizer.next_is(Token::Dot) {
-        tokenizer.expect_next(Token::Dot)?;
-        if tokenizer.next_is(Token::Count) {
-            fill = Fill::Count;
-
-            tokenizer.expect_next(Token::Count)?;
-        } else {
-            tokenizer.expect_next(Token::Fill)?;
-            tokenizer.expect_next(Token::RoundOpen)?;
-
-            let num =
-                if let Token::Number(num) = tokenizer.expect_next(Token::Number(String::new()))? {
-                    num
-                } else {
-                    unreachable!()
-                };
-            tokenizer.expect_next(Token::RoundClose)?;
-
-            if num == "0" {
-                fill = Fill::Fill(false);
-            } else if num == "1" {
-                fill = Fill::Fill(true);
-            } else {
-                return Err(
-                    tokenizer.error(&format!("unexpected char {} only '0' or '1' allowed", num))
-                );
-            }
-        }
-    } else {
-        fill = Fill::None;
+    /// the buried signals declared with `node`, they go to OLMCs without a pin
+    pub fn nodes(&self) -> &Vec<String> {
+        &self.nodes
     }
 
-    tokenizer.expect_next(Token::CurlyOpen)?;
-
-    let table = bool_table(tokenizer)?;
-
-    let table = match fill {
-        Fill::Count => bool_algebra::parse_count(
-            in_names.len(),
-            out_names.len(),
-            table,
-            crate::COUNT_VERTICAL,
-        ),
-        Fill::Fill(fill) => bool_algebra::parse_fill(in_names.len(), out_names.len(), table, fill),
-        Fill::None => bool_algebra::parse_full(in_names.len(), out_names.len(), table),
-    };
-
-    let table = match table {
-        Ok(table) => table,
-        Err(msg) => return Err(tokenizer.error(&msg)),
-    };
-
-    let in_names = in_names.iter().map(|s| s.as_ref()).collect();
-    let out_names = out_names.iter().map(|s| s.as_ref()).collect();
-    let lt = LookupTable::new(table, in_names, out_names, "").unwrap();
-    lut.push(lt);
-
-    tokenizer.expect_next(Token::CurlyClose)?;
-
-    Ok(())
-}
-
-fn pin_num(tokenizer: &mut Tokenizer<Token>) -> Result<Vec<usize>, Error> {
-    if tokenizer.next_is(Token::SquareOpen) {
-        tokenizer.expect_next(Token::SquareOpen)?;
-        let start = get_num(tokenizer)?;
-        tokenizer.expect_next(Token::Dot)?;
-        tokenizer.expect_next(Token::Dot)?;
-        let end = get_num(tokenizer)?;
-
-        let mut result = Vec::new();
-        for num in start..end + 1 {
-            result.push(num);
-        }
-
-        tokenizer.expect_next(Token::SquareClose)?;
-        Ok(result)
-    } else {
-        let mut result = Vec::new();
-
-        let num = get_num(tokenizer)?;
-        result.push(num);
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
 
-        while tokenizer.next_is(Token::Comma) {
-            tokenizer.expect_next(Token::Comma)?;
-            let num = get_num(tokenizer)?;
-            result.push(num);
-        }
+    pub fn tests(&self) -> &Vec<TestBlock> {
+        &self.tests
+    }
 
-        Ok(result)
+    pub fn assertions(&self) -> &Vec<Assertion> {
+        &self.assertions
     }
-}
 
-fn get_num(tokenizer: &mut Tokenizer<Token>) -> Result<usize, Error> {
-    if let Token::Number(num) = tokenizer.expect_next(Token::Number(String::new()))? {
-        match num.parse() {
-            Ok(num) => Ok(num),
-            Err(err) => Err(tokenizer.error(&format!("ParseIntError: {}", err))),
+    /// the tables the translator needs, every signal has to be assigned to a pin
+    pub fn table_data(&self) -> Result<Vec<TableData>, String> {
+        match ogal2td(self.clone()) {
+            Ok(tables) => Ok(tables),
+            Err(err) => Err(format!("{:?}", err)),
         }
-    } else {
-        unreachable!();
     }
-}
 
-fn pin_name(tokenizer: &mut Tokenizer<Token>) -> Result<Vec<String>, Error> {
-    let name = get_name(tokenizer)?;
-    if tokenizer.next_is(Token::SquareOpen) {
-        let mut result = Vec::new();
-        let nums = pin_num(tokenizer)?;
-        for num in nums {
-            result.push(format!("{}{}", name, num));
+    /// runs the test vectors and proves the assertions
+    pub fn verify(&self) -> Result<(), String> {
+        for test in self.tests.iter() {
+            test.run(self)?;
         }
+        for assertion in self.assertions.iter() {
+            assertion.check(self)?;
+        }
+        Ok(())
+    }
 
-        Ok(result)
-    } else {
+    /// the `V` fields of all test blocks in the order they appear in the source
+    pub fn jedec_vectors(&self, config: &crate::CircuitConfig) -> Result<Vec<String>, String> {
         let mut result = Vec::new();
-        result.push(name);
-
-        while tokenizer.next_is(Token::Comma) {
-            tokenizer.expect_next(Token::Comma)?;
-            let name = get_name(tokenizer)?;
-            result.push(name);
+        for test in self.tests.iter() {
+            result.append(&mut test.jedec(self, config)?);
         }
-
         Ok(result)
     }
-}
 
-fn get_name(tokenizer: &mut Tokenizer<Token>) -> Result<String, Error> {
-    if let Token::Identifier(name) = tokenizer.expect_next(Token::Identifier(String::new()))? {
-        Ok(name)
-    } else {
-        unreachable!();
+    pub fn parse(code: &str) -> Result<Self, Error> {
+        Self::parse_with(code, ParserOptions::default())
     }
-}
 
-fn bool_table(tokenizer: &mut Tokenizer<Token>) -> Result<Vec<bool>, Error> {
-    let mut result = Vec::new();
-
-    while tokenizer.next_is(Token::Number(String::new())) {
-        if let Token::Number(num) = tokenizer.expect_next(Token::Number(String::new()))? {
-            let temp = match get_bool(num) {
-                Ok(temp) => temp,
-                Err(msg) => return Err(tokenizer.error(&msg)),
-            };
-
-            for b in temp {
-                result.push(b);
-            }
-        } else {
-            unreachable!();
+    /// parses with the given dialect, `#pragma` lines in the code override it from there on
+    pub fn parse_with(code: &str, options: ParserOptions) -> Result<Self, Error> {
+        let ast = match Ast::parse_with(code, options.clone()) {
+            Ok(ast) => ast,
+            Err(err) => return Err(to_error(code, &err.into())),
+        };
+        match OGal::from_ast(&ast, options) {
+            Ok(o_gal) => Ok(o_gal),
+            Err(diagnostic) => Err(to_error(code, &diagnostic)),
         }
     }
-
-    Ok(result)
 }
 
-fn get_bool(num: String) -> Result<Vec<bool>, String> {
-    let mut result = Vec::new();
-    for c in num.chars() {
-        if c == '0' {
-            result.push(false);
-        } else if c == '1' {
-            result.push(true);
-        } else {
-            return Err(format!("unexpected char {} only '0' or '1' allowed", c));
+/// the message of a diagnostic with the line and column it points to
+fn to_error(code: &str, diagnostic: &Diagnostic) -> Error {
+    match diagnostic.span {
+        Some(span) => {
+            let (line, column) = span.position(code);
+            Error::msg(&format!("{}:{}: {}", line + 1, column + 1, diagnostic.msg))
         }
+        None => Error::msg(&diagnostic.msg),
     }
-
-    Ok(result)
 }
 
 pub fn ogal2td(o_gal: OGal) -> Result<Vec<TableData>, Error> {
@@ -362,20 +195,65 @@ pub fn ogal2td(o_gal: OGal) -> Result<Vec<TableData>, Error> {
     let mut td_vec = Vec::with_capacity(o_gal.lut.len());
 
     for lut in o_gal.lut {
-        let out_pins = lookup_pins(lut.out_names(), &o_gal.pins)?;
+        let out_names = lut.out_names();
+        let out_pins = lookup_pins(out_names.clone(), &o_gal.pins)?;
         let dffs = lookup_pins(o_gal.dff.clone(), &o_gal.pins)?;
+        let latches = lookup_pins(o_gal.latch.clone(), &o_gal.pins)?;
+        let hazard_free = lookup_pins(o_gal.hazard_free.clone(), &o_gal.pins)?;
 
         for (i, &out_pin) in out_pins.iter().enumerate() {
-            let td = TableData::new(
+            let mut td = TableData::new(
                 lookup_pins(lut.in_names(), &o_gal.pins)?,
                 out_pin,
                 lut.get_table()[i].clone(),
                 dffs.contains(&out_pin),
             );
+            td.hazard_free = latches.contains(&out_pin) || hazard_free.contains(&out_pin);
+            for (_, control, lut) in o_gal.controls.iter().filter(|(n, _, _)| *n == out_names[i]) {
+                td.controls.push(ControlTable {
+                    control: *control,
+                    input_pins: lookup_pins(lut.in_names(), &o_gal.pins)?,
+                    table: lut.get_table()[0].clone(),
+                });
+            }
+            td.helper_pins = o_gal
+                .helper
+                .iter()
+                .filter(|(name, _)| out_names[i] == *name)
+                .flat_map(|(_, pins)| pins.iter().map(|&pin| pin as u32))
+                .collect();
+            if td_vec
+                .iter()
+                .any(|other: &TableData| other.output_pin == out_pin)
+            {
+                return Err(Error::msg(&format!(
+                    "pin {} is driven by more than one table",
+                    out_pin
+                )));
+            }
             td_vec.push(td);
         }
     }
 
+    for (name, control, _) in o_gal.controls.iter() {
+        let pin = lookup_pins(vec![name.clone()], &o_gal.pins)?[0];
+        if !td_vec.iter().any(|td| td.output_pin == pin) {
+            return Err(Error::msg(&format!(
+                "{}.{} is set but {} isn't driven",
+                name,
+                control.name(),
+                name
+            )));
+        } else if control.needs_register() && !o_gal.dff.contains(name) {
+            return Err(Error::msg(&format!(
+                "{}.{} needs a register, add `{}.dff;`",
+                name,
+                control.name(),
+                name
+            )));
+        }
+    }
+
     Ok(td_vec)
 }
 
@@ -417,8 +295,14 @@ pub enum Token {
     And, // &
     #[token("|")]
     Or, // |
+    #[token("|=")]
+    OrEquals, // |=
     #[token("^")]
     Xor, // ^
+    #[token("#")]
+    CuplOr, // #
+    #[token("$")]
+    CuplXor, // $
     #[token("!")]
     Not, // !
 
@@ -445,6 +329,15 @@ pub enum Token {
     #[regex(r"[0-9]+", |lex| lex.slice().parse())]
     Number(String),
 
+    #[regex(r"0b[01]+", |lex| lex.slice()[2..].to_string())]
+    Binary(String),
+
+    #[regex(r"#pragma[^\r\n]*", |lex| lex.slice()["#pragma".len()..].to_string())]
+    Pragma(String),
+
+    #[regex(r#""[^"\r\n]*""#, |lex| lex.slice().trim_matches('"').to_string())]
+    Str(String),
+
     #[token("\t", ignore)]
     #[token(" ", ignore)]
     #[token("\n", ignore)]
@@ -462,6 +355,9 @@ impl TypeEq for Token {
             (Token::Ignore(_), Token::Ignore(_)) => true,
             (Token::Number(_), Token::Number(_)) => true,
             (Token::Identifier(_), Token::Identifier(_)) => true,
+            (Token::Str(_), Token::Str(_)) => true,
+            (Token::Binary(_), Token::Binary(_)) => true,
+            (Token::Pragma(_), Token::Pragma(_)) => true,
             _ => self == other,
         }
     }
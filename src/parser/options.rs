@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+/// Selects the dialect of the source language.
+///
+/// The options can be given to `OGal::parse_with` or changed inside a file with a `#pragma` line,
+/// which applies to everything after it e.g.
+///
+/// ```text
+/// #pragma name_first
+/// pin a = 1;
+/// #pragma cupl_operators
+/// y = a # b $ c;
+/// ```
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ParserOptions {
+    /// `pin 1 = a;` if true and `pin a = 1;` if false
+    pub num_first: bool,
+    /// each line of a `.count` table holds all rows of one output instead of one row of all outputs
+    pub count_vertical: bool,
+    /// accept `#` for or and `$` for xor like CUPL does
+    pub cupl_operators: bool,
+    /// directory the files of `rom(...) from "file";` are relative to, the current directory if none
+    pub rom_dir: Option<String>,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            num_first: true,
+            count_vertical: false,
+            cupl_operators: false,
+            rom_dir: None,
+        }
+    }
+}
+
+impl ParserOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// applies the text after `#pragma`
+    pub fn pragma(&mut self, pragma: &str) -> Result<(), String> {
+        match pragma.trim() {
+            "num_first" => self.num_first = true,
+            "name_first" => self.num_first = false,
+            "count_vertical" => self.count_vertical = true,
+            "count_horizontal" => self.count_vertical = false,
+            "cupl_operators" => self.cupl_operators = true,
+            "ogal_operators" => self.cupl_operators = false,
+            other => match crate::lint::pragma(other) {
+                // lint levels are collected by `lint::check`
+                Some(result) => return result.map(|_| ()),
+                None => {
+                    return Err(format!(
+                        "unknown pragma {} expected num_first, name_first, count_vertical, count_horizontal, cupl_operators, ogal_operators, allow(..), warn(..) or deny(..)",
+                        other
+                    ))
+                }
+            },
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use super::{ogal2td, OGal};
+use crate::translator::dnf::{term_table, Expression, Row};
+use crate::translator::split::split;
+use crate::CircuitConfig;
+use std::fmt;
+
+/// a static-1 hazard of an output, see `OGal::hazards`
+#[derive(PartialEq, Debug, Clone)]
+pub struct Hazard {
+    pub output: String,
+    /// the input whose change can glitch the output to 0 for a moment
+    pub input: String,
+    /// the levels of the other inputs while it changes
+    pub levels: Vec<(String, bool)>,
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let levels: Vec<String> = self
+            .levels
+            .iter()
+            .map(|(name, level)| format!("{} = {}", name, *level as u8))
+            .collect();
+        write!(f, "{} can glitch when {} changes", self.output, self.input)?;
+        if !levels.is_empty() {
+            write!(f, " while {}", levels.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl OGal {
+    /// Describes how the design fits into the device, one block per output with the product
+    /// terms it needs out of the terms its OLMC has e.g.
+    ///
+    /// ```text
+    /// pin 14 q: 3 of 8 terms, combinational, latch
+    ///     d & g
+    ///     q & !g
+    ///     d & q // consensus
+    /// ```
+    ///
+    /// Terms marked as consensus don't change the function, they are kept so the output doesn't
+    /// glitch when a single input changes. Outputs with too many terms are split over helper
+    /// OLMCs, each of them makes the output one propagation delay slower. Controls like `.oe`
+    /// follow the terms with their product term.
+    pub fn fit_report(&self, config: &CircuitConfig) -> Result<String, String> {
+        let tables = match ogal2td(self.clone()) {
+            Ok(tables) => tables,
+            Err(err) => return Err(format!("{:?}", err)),
+        };
+        let mut exprs = Vec::new();
+        for table in tables.iter() {
+            exprs.push(Expression::new(table, config)?);
+        }
+        let (exprs, splits) = split(exprs, &tables, config)?;
+
+        let mut report = String::new();
+        for expr in exprs.iter() {
+            let terms = match config.terms(expr.out_pin, expr.enable_flip_flop) {
+                Some(terms) => terms,
+                None => return Err(format!("pin {} is not an output", expr.out_pin)),
+            };
+            let table = tables.iter().find(|table| table.output_pin == expr.out_pin);
+            let split = splits.iter().find(|split| split.out_pin == expr.out_pin);
+
+            let mut kind = if expr.enable_flip_flop {
+                "registered".to_string()
+            } else {
+                "combinational".to_string()
+            };
+            let name = match table {
+                Some(_) => self.pin_name(expr.out_pin),
+                None => {
+                    let output = splits
+                        .iter()
+                        .find(|split| split.helpers.contains(&expr.out_pin))
+                        .map_or(0, |split| split.out_pin);
+                    format!("(helper of {})", self.pin_name(output))
+                }
+            };
+            if self.latch.contains(&name) {
+                kind.push_str(", latch");
+            } else if self.hazard_free.contains(&name) {
+                kind.push_str(", hazard free");
+            }
+            if let Some(split) = split {
+                let helpers: Vec<String> =
+                    split.helpers.iter().map(|pin| pin.to_string()).collect();
+                kind.push_str(&format!(
+                    ", split over pin {} (+{} propagation delay)",
+                    helpers.join(", "),
+                    helpers.len()
+                ));
+            }
+
+            report.push_str(&format!(
+                "pin {} {}: {} of {} terms, {}\n",
+                expr.out_pin,
+                name,
+                expr.rows.len(),
+                terms,
+                kind
+            ));
+            for (i, row) in expr.rows.iter().enumerate() {
+                // only the terms of an output which isn't split are compared with its table
+                let consensus = match table {
+                    Some(table) if split.is_none() => {
+                        let others: Vec<&Row> = expr
+                            .rows
+                            .iter()
+                            .enumerate()
+                            .filter(|&(j, _)| j != i)
+                            .map(|(_, row)| row)
+                            .collect();
+                        (0..table.table.len()).all(|index| {
+                            !row.covers(&table.input_pins, index)
+                                || others
+                                    .iter()
+                                    .any(|other| other.covers(&table.input_pins, index))
+                        })
+                    }
+                    _ => false,
+                };
+
+                report.push_str(&format!("    {}", self.term(row)));
+                if consensus {
+                    report.push_str(" // consensus");
+                }
+                report.push('\n');
+            }
+            for (control, row) in expr.controls.iter() {
+                let term = row.as_ref().map_or("0".to_string(), |row| self.term(row));
+                report.push_str(&format!("    .{} = {}\n", control.name(), term));
+            }
+        }
+        Ok(report)
+    }
+
+    /// Lists the static-1 hazards of the terms the outputs are programmed with. They can be removed
+    /// with `y.hazard_free;` which adds the consensus terms.
+    pub fn hazards(&self, config: &CircuitConfig) -> Result<Vec<Hazard>, String> {
+        let tables = match ogal2td(self.clone()) {
+            Ok(tables) => tables,
+            Err(err) => return Err(format!("{:?}", err)),
+        };
+
+        let mut result = Vec::new();
+        for table in tables.iter() {
+            let expr = Expression::new(table, config)?;
+            let terms = term_table(table, config);
+            for (index, i) in expr.static_hazards(&terms, &table.input_pins) {
+                let levels = table
+                    .input_pins
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(j, &pin)| (self.pin_name(pin), index >> j & 1 == 1))
+                    .collect();
+                result.push(Hazard {
+                    output: self.pin_name(table.output_pin),
+                    input: self.pin_name(table.input_pins[i]),
+                    levels,
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    fn term(&self, row: &Row) -> String {
+        let literals: Vec<String> = row
+            .pins
+            .iter()
+            .map(|pin| {
+                let name = self.pin_name(pin.pin_num);
+                if pin.inverted {
+                    format!("!{}", name)
+                } else {
+                    name
+                }
+            })
+            .collect();
+        if literals.is_empty() {
+            "1".to_string()
+        } else {
+            literals.join(" & ")
+        }
+    }
+
+    fn pin_name(&self, pin_num: u32) -> String {
+        match self.pins.iter().find(|&(_, &num)| num == pin_num as usize) {
+            Some((name, _)) => name.clone(),
+            None => pin_num.to_string(),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::Path;
+
+/// the widest address of a rom, its lookup table has a row for every address
+pub const MAX_ADDR_BITS: usize = 20;
+
+/// Reads the words of a `rom(...) from "file";` statement.
+///
+/// The format is picked by the file extension: `.hex` is Intel HEX, `.csv` holds one word per
+/// line either as `data` or `address, data` and everything else is read as raw binary. Words of
+/// more than 8 bits take up several bytes in the binary formats (little endian). Addresses which
+/// aren't in the file are 0.
+pub fn load(path: &Path, addr_bits: usize, data_bits: usize) -> Result<Vec<u64>, String> {
+    if data_bits > 64 {
+        return Err(format!(
+            "rom data width {} is larger than 64 bits",
+            data_bits
+        ));
+    }
+    if addr_bits > MAX_ADDR_BITS {
+        return Err(format!(
+            "rom address width {} is larger than {} bits",
+            addr_bits, MAX_ADDR_BITS
+        ));
+    }
+    let read_err = |err: std::io::Error| format!("can't read {}: {}", path.display(), err);
+
+    let num_words = 1_usize << addr_bits;
+    let words = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("hex") => {
+            let text = fs::read_to_string(path).map_err(read_err)?;
+            let word_len = data_bits.div_ceil(8).max(1);
+            bytes_to_words(&intel_hex(&text, num_words * word_len)?, data_bits)
+        }
+        Some("csv") => csv(&fs::read_to_string(path).map_err(read_err)?, num_words)?,
+        _ => bytes_to_words(&fs::read(path).map_err(read_err)?, data_bits),
+    };
+
+    if words.len() > num_words {
+        return Err(format!(
+            "{} holds {} words but {} address bits only address {}",
+            path.display(),
+            words.len(),
+            addr_bits,
+            num_words
+        ));
+    }
+    for (address, &word) in words.iter().enumerate() {
+        if data_bits < 64 && word >> data_bits != 0 {
+            return Err(format!(
+                "{} word {:#x} at address {:#x} doesn't fit into {} data bits",
+                path.display(),
+                word,
+                address,
+                data_bits
+            ));
+        }
+    }
+
+    let mut result = words;
+    result.resize(num_words, 0);
+    Ok(result)
+}
+
+fn bytes_to_words(bytes: &[u8], data_bits: usize) -> Vec<u64> {
+    let word_len = data_bits.div_ceil(8).max(1);
+    bytes
+        .chunks(word_len)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |word, (i, &byte)| word | (byte as u64) << (8 * i))
+        })
+        .collect()
+}
+
+/// decodes the data (00), end of file (01), extended segment (02) and extended linear (04) records,
+/// every byte has to be below `num_bytes`
+fn intel_hex(text: &str, num_bytes: usize) -> Result<Vec<u8>, String> {
+    let mut result = Vec::new();
+    let mut base = 0;
+
+    for (num, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let err = |msg: &str| format!("intel hex line {}: {}", num + 1, msg);
+
+        if !line.starts_with(':') || line.len() < 11 || line.len() % 2 == 0 {
+            return Err(err("malformed record"));
+        }
+        let mut bytes = Vec::new();
+        for i in (1..line.len()).step_by(2) {
+            match u8::from_str_radix(&line[i..i + 2], 16) {
+                Ok(byte) => bytes.push(byte),
+                Err(_) => return Err(err("invalid hex digit")),
+            }
+        }
+
+        let len = bytes[0] as usize;
+        if bytes.len() != len + 5 {
+            return Err(err("record length doesn't match"));
+        }
+        if bytes.iter().fold(0_u8, |sum, &byte| sum.wrapping_add(byte)) != 0 {
+            return Err(err("wrong checksum"));
+        }
+
+        let address = (bytes[1] as usize) << 8 | bytes[2] as usize;
+        let data = &bytes[4..4 + len];
+        match bytes[3] {
+            0x00 => {
+                for (i, &byte) in data.iter().enumerate() {
+                    let address = base + address + i;
+                    if address >= num_bytes {
+                        return Err(err(&format!(
+                            "address {:#x} is out of the rom which has {} bytes",
+                            address, num_bytes
+                        )));
+                    }
+                    if result.len() <= address {
+                        result.resize(address + 1, 0);
+                    }
+                    result[address] = byte;
+                }
+            }
+            0x01 => break,
+            0x02 if len == 2 => base = ((data[0] as usize) << 8 | data[1] as usize) << 4,
+            0x04 if len == 2 => base = ((data[0] as usize) << 8 | data[1] as usize) << 16,
+            0x03 | 0x05 => (),
+            _ => return Err(err("unsupported record type")),
+        }
+    }
+
+    Ok(result)
+}
+
+/// numbers can be decimal, `0x` hex or `0b` binary, lines starting with `#` are comments, every
+/// address has to be below `num_words`
+fn csv(text: &str, num_words: usize) -> Result<Vec<u64>, String> {
+    let mut result = Vec::new();
+
+    for (num, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let err = |msg: String| format!("csv line {}: {}", num + 1, msg);
+
+        let mut values = Vec::new();
+        for field in line.split(',') {
+            values.push(csv_num(field.trim()).map_err(err)?);
+        }
+
+        let (address, word) = match values[..] {
+            [word] => (result.len() as u64, word),
+            [address, word] => (address, word),
+            _ => return Err(err("expected `data` or `address, data`".to_string())),
+        };
+        if address >= num_words as u64 {
+            return Err(err(format!(
+                "address {:#x} is out of the rom which has {} words",
+                address, num_words
+            )));
+        }
+        let address = address as usize;
+        if result.len() <= address {
+            result.resize(address + 1, 0);
+        }
+        result[address] = word;
+    }
+
+    Ok(result)
+}
+
+fn csv_num(field: &str) -> Result<u64, String> {
+    let result = if let Some(hex) = field.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = field.strip_prefix("0b") {
+        u64::from_str_radix(bin, 2)
+    } else {
+        field.parse()
+    };
+    result.map_err(|err| format!("can't parse {}: {}", field, err))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn intel_hex() {
+        let text = ":0400000001020304F2\n:020000040000FA\n:00000001FF\n";
+        assert_eq!(super::intel_hex(text, 4), Ok(vec![1, 2, 3, 4]));
+        assert!(super::intel_hex(":0400000001020304F3", 4).is_err());
+        assert!(super::intel_hex(text, 3).is_err());
+        // an extended linear address far out of the rom
+        let text = ":02000004FFFFFC\n:0100000001FE\n";
+        assert!(super::intel_hex(text, 1 << 20)
+            .unwrap_err()
+            .contains("address 0xffff0000 is out of the rom"));
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            super::csv("1\n0x2\n# comment\n5, 0b11", 8),
+            Ok(vec![1, 2, 0, 0, 0, 3])
+        );
+        assert!(super::csv("1, 2, 3", 8).is_err());
+        assert!(super::csv("0xFFFFFFFFFFFF, 1", 8).is_err());
+        assert!(super::csv("18446744073709551615, 1", 8).is_err());
+        assert!(super::csv("8, 1", 8).is_err());
+    }
+
+    #[test]
+    fn bytes_to_words() {
+        assert_eq!(super::bytes_to_words(&[1, 2, 3], 4), vec![1, 2, 3]);
+        assert_eq!(super::bytes_to_words(&[0x34, 0x12], 16), vec![0x1234]);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::test_vector::Level;
+use crate::OGal;
+use hardware_sim::Component;
+use std::collections::HashMap;
+
+/// Three valued simulation of an `OGal` design where `None` stands for an unknown signal.
+///
+/// Registers start at 0 like the power-up reset of a GAL, combinational outputs are evaluated
+/// until all feedback paths have settled.
+pub struct Simulator<'a> {
+    o_gal: &'a OGal,
+    signals: HashMap<String, Option<bool>>,
+}
+
+impl<'a> Simulator<'a> {
+    pub fn new(o_gal: &'a OGal) -> Self {
+        let mut signals = HashMap::new();
+        for name in o_gal.dff.iter() {
+            signals.insert(name.clone(), Some(false));
+        }
+        Self { o_gal, signals }
+    }
+
+    /// applies the inputs and clocks the registers once if `clock` is set
+    pub fn step(
+        &mut self,
+        inputs: &HashMap<String, Option<bool>>,
+        clock: bool,
+    ) -> Result<(), String> {
+        for (name, &val) in inputs.iter() {
+            if !self.is_driven(name) {
+                self.signals.insert(name.clone(), val);
+            }
+        }
+        self.settle()?;
+
+        if clock {
+            let mut next = Vec::new();
+            for lut in self.o_gal.lut.iter() {
+                for (i, name) in lut.out_names().iter().enumerate() {
+                    if self.o_gal.dff.contains(name) {
+                        next.push((
+                            name.clone(),
+                            self.eval(&lut.in_names(), &lut.get_table()[i]),
+                        ));
+                    }
+                }
+            }
+            for (name, val) in next {
+                self.signals.insert(name, val);
+            }
+            self.settle()?;
+        }
+
+        Ok(())
+    }
+
+    /// sets a signal regardless of what drives it, used to put registers into an arbitrary state
+    pub fn force(&mut self, name: &str, val: Option<bool>) {
+        self.signals.insert(name.to_string(), val);
+    }
+
+    pub fn get(&self, name: &str) -> Option<bool> {
+        self.signals.get(name).cloned().flatten()
+    }
+
+    pub fn level(&self, name: &str) -> Level {
+        if !self.is_driven(name) && !self.signals.contains_key(name) {
+            return Level::HighZ;
+        }
+        match self.get(name) {
+            Some(true) => Level::High,
+            Some(false) => Level::Low,
+            None => Level::DontCare,
+        }
+    }
+
+    fn is_driven(&self, name: &str) -> bool {
+        self.o_gal
+            .lut
+            .iter()
+            .any(|lut| lut.out_names().iter().any(|out| out == name))
+    }
+
+    fn settle(&mut self) -> Result<(), String> {
+        for _ in 0..=self.o_gal.lut.len() {
+            let mut changed = Vec::new();
+            for lut in self.o_gal.lut.iter() {
+                for (i, name) in lut.out_names().iter().enumerate() {
+                    if self.o_gal.dff.contains(name) {
+                        continue;
+                    }
+                    let val = self.eval(&lut.in_names(), &lut.get_table()[i]);
+                    if self.signals.get(name) != Some(&val) {
+                        changed.push((name.clone(), val));
+                    }
+                }
+            }
+
+            if changed.is_empty() {
+                return Ok(());
+            }
+            for (name, val) in changed {
+                self.signals.insert(name, val);
+            }
+        }
+
+        Err("combinational feedback doesn't settle".to_string())
+    }
+
+    pub fn eval(&self, in_names: &[String], table: &[bool]) -> Option<bool> {
+        let inputs: Vec<Option<bool>> = in_names.iter().map(|name| self.get(name)).collect();
+        eval_table(table, &inputs)
+    }
+}
+
+/// Looks up a truth table where the first input is the most significant bit of the row index.
+/// Unknown inputs are tried with both values, the result is only known if all of them agree.
+pub fn eval_table(table: &[bool], inputs: &[Option<bool>]) -> Option<bool> {
+    let mut index = 0;
+    let mut unknown = Vec::new();
+    for (i, input) in inputs.iter().enumerate() {
+        let bit = 1 << (inputs.len() - 1 - i);
+        match input {
+            Some(true) => index |= bit,
+            Some(false) => (),
+            None => unknown.push(bit),
+        }
+    }
+
+    if unknown.len() > 16 {
+        return None;
+    }
+
+    let first = table[index];
+    for combination in 1..(1_usize << unknown.len()) {
+        let mut i = index;
+        for (j, bit) in unknown.iter().enumerate() {
+            if combination & (1 << j) != 0 {
+                i |= bit;
+            }
+        }
+        if table[i] != first {
+            return None;
+        }
+    }
+    Some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn eval_table() {
+        let and = vec![false, false, false, true];
+        assert_eq!(
+            super::eval_table(&and, &[Some(true), Some(true)]),
+            Some(true)
+        );
+        assert_eq!(super::eval_table(&and, &[Some(false), None]), Some(false));
+        assert_eq!(super::eval_table(&and, &[Some(true), None]), None);
+
+        let not = vec![true, false];
+        assert_eq!(super::eval_table(&not, &[Some(true)]), Some(false));
+    }
+}
@@ -7,12 +7,67 @@ use serde::{Deserialize, Serialize};
 /// - "output_pin" stores the output pin
 /// - "table" contains the truth table for the expression and is used to generate a dnf expression later on
 /// - "enable_flip_flop" holds a boolean which decides if the output pin should have its flip flop turned on.
+/// - "hazard_free" writes every prime implicant instead of one term per true row, so the output
+///   doesn't glitch when a single input changes (needed for latches built from feedback)
+/// - "helper_pins" are the OLMCs the terms go to which don't fit into the one of the output pin,
+///   if it is empty unused OLMCs are picked
+/// - "controls" are the signals of the OLMC which come from a product term e.g. `y.oe = a;`
 #[derive(PartialEq, Debug, Clone, Hash, Serialize, Deserialize)]
 pub struct TableData {
     pub input_pins: Vec<u32>,
     pub output_pin: u32,
     pub table: Vec<bool>,
     pub enable_flip_flop: bool,
+    #[serde(default)]
+    pub hazard_free: bool,
+    #[serde(default)]
+    pub helper_pins: Vec<u32>,
+    #[serde(default)]
+    pub controls: Vec<ControlTable>,
+}
+
+/// a signal of an OLMC which comes from its own product term instead of a pin
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Control {
+    /// `y.oe = expr;` the output enable
+    Oe,
+    /// `y.clk = expr;` the clock of the register of a device with a clock term per OLMC
+    Clk,
+    /// `y.ar = expr;` the asynchronous reset of the register
+    Ar,
+    /// `y.ap = expr;` the asynchronous preset of the register
+    Ap,
+}
+
+impl Control {
+    pub const ALL: [Control; 4] = [Control::Oe, Control::Clk, Control::Ar, Control::Ap];
+
+    /// the attribute after the name e.g. `oe`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Control::Oe => "oe",
+            Control::Clk => "clk",
+            Control::Ar => "ar",
+            Control::Ap => "ap",
+        }
+    }
+
+    /// if the control drives the register of the OLMC and needs `.dff`
+    pub fn needs_register(&self) -> bool {
+        *self != Control::Oe
+    }
+
+    pub fn from_name(name: &str) -> Option<Control> {
+        Control::ALL.iter().find(|c| c.name() == name).cloned()
+    }
+}
+
+/// the truth table of a control over `input_pins`, it has to fit into a single product term
+#[derive(PartialEq, Debug, Clone, Hash, Serialize, Deserialize)]
+pub struct ControlTable {
+    pub control: Control,
+    pub input_pins: Vec<u32>,
+    pub table: Vec<bool>,
 }
 
 impl TableData {
@@ -27,6 +82,9 @@ impl TableData {
             output_pin,
             table,
             enable_flip_flop,
+            hazard_free: false,
+            helper_pins: Vec::new(),
+            controls: Vec::new(),
         }
     }
 
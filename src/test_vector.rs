@@ -0,0 +1,168 @@
+use crate::simulator::Simulator;
+use crate::{CircuitConfig, OGal};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One column of a test vector.
+///
+/// Inputs may be `0`, `1`, `C` (clock pulse) or `X` (don't care), expected outputs may be
+/// `L`/`0`, `H`/`1`, `Z` (high impedance) or `X`.
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Level {
+    Low,
+    High,
+    Clock,
+    HighZ,
+    DontCare,
+}
+
+impl Level {
+    pub fn from_input(c: char) -> Result<Self, String> {
+        match c {
+            '0' => Ok(Level::Low),
+            '1' => Ok(Level::High),
+            'C' => Ok(Level::Clock),
+            'X' => Ok(Level::DontCare),
+            _ => Err(format!(
+                "unexpected input {} only '0', '1', 'C' or 'X' allowed",
+                c
+            )),
+        }
+    }
+
+    pub fn from_output(c: char) -> Result<Self, String> {
+        match c {
+            '0' | 'L' => Ok(Level::Low),
+            '1' | 'H' => Ok(Level::High),
+            'Z' => Ok(Level::HighZ),
+            'X' => Ok(Level::DontCare),
+            _ => Err(format!(
+                "unexpected output {} only '0', '1', 'L', 'H', 'Z' or 'X' allowed",
+                c
+            )),
+        }
+    }
+
+    pub(crate) fn input_char(&self) -> char {
+        match self {
+            Level::Low => '0',
+            Level::High => '1',
+            Level::Clock => 'C',
+            _ => 'X',
+        }
+    }
+
+    pub(crate) fn output_char(&self) -> char {
+        match self {
+            Level::Low => 'L',
+            Level::High => 'H',
+            Level::HighZ => 'Z',
+            _ => 'X',
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct TestVector {
+    pub inputs: Vec<Level>,
+    pub outputs: Vec<Level>,
+}
+
+/// A `test { a b -> y; 0 0 -> 0; }` block. Every vector is applied after the previous one, so
+/// registered outputs keep their state from vector to vector starting at the power-up state.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct TestBlock {
+    pub in_names: Vec<String>,
+    pub out_names: Vec<String>,
+    pub vectors: Vec<TestVector>,
+}
+
+impl TestBlock {
+    /// simulates the design and returns an error describing the first vector that doesn't match
+    pub fn run(&self, o_gal: &OGal) -> Result<(), String> {
+        let mut sim = Simulator::new(o_gal);
+
+        for (num, vector) in self.vectors.iter().enumerate() {
+            let mut inputs = HashMap::new();
+            let mut clock = false;
+            for (name, level) in self.in_names.iter().zip(vector.inputs.iter()) {
+                let val = match level {
+                    Level::Low => Some(false),
+                    Level::High => Some(true),
+                    Level::Clock => {
+                        clock = true;
+                        Some(false)
+                    }
+                    _ => None,
+                };
+                inputs.insert(name.clone(), val);
+            }
+
+            sim.step(&inputs, clock)?;
+
+            for (name, &expected) in self.out_names.iter().zip(vector.outputs.iter()) {
+                let actual = sim.level(name);
+                if expected != Level::DontCare && actual != expected {
+                    return Err(format!(
+                        "test vector {} failed: expected {} = {} but got {}",
+                        num + 1,
+                        name,
+                        expected.output_char(),
+                        actual.output_char()
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// formats every vector as the pin ordered string of a JEDEC `V` field
+    pub fn jedec(&self, o_gal: &OGal, config: &CircuitConfig) -> Result<Vec<String>, String> {
+        let mut in_pins = Vec::new();
+        let mut out_pins = Vec::new();
+        for name in self.in_names.iter() {
+            in_pins.push(pin_index(name, o_gal, config)?);
+        }
+        for name in self.out_names.iter() {
+            out_pins.push(pin_index(name, o_gal, config)?);
+        }
+
+        let mut template = vec!['X'; config.num_pins as usize];
+        for pin in 1..=config.num_pins {
+            let used = config.inputs.contains(&pin)
+                || config.outputs.iter().any(|&(out, _)| out == pin)
+                || config
+                    .special_pins
+                    .iter()
+                    .any(|&(special, _)| special == pin);
+            if !used {
+                template[(pin - 1) as usize] = 'N';
+            }
+        }
+
+        let mut result = Vec::new();
+        for vector in self.vectors.iter() {
+            let mut line = template.clone();
+            for (&pin, level) in in_pins.iter().zip(vector.inputs.iter()) {
+                line[pin] = level.input_char();
+            }
+            for (&pin, level) in out_pins.iter().zip(vector.outputs.iter()) {
+                line[pin] = level.output_char();
+            }
+            result.push(line.iter().collect());
+        }
+        Ok(result)
+    }
+}
+
+fn pin_index(name: &str, o_gal: &OGal, config: &CircuitConfig) -> Result<usize, String> {
+    match o_gal.pins.get(name) {
+        Some(&pin) if pin >= 1 && pin <= config.num_pins as usize => Ok(pin - 1),
+        Some(&pin) => Err(format!(
+            "pin {} of {} doesn't exist on the device",
+            pin, name
+        )),
+        None => Err(format!("pin {} not defined", name)),
+    }
+}
@@ -3,17 +3,36 @@ use crate::{CircuitConfig, TableData};
 
 use super::dnf::Expression;
 
+/// `head` is written between STX and the first field, without it only the version is written.
+/// `Header::jedec` makes one from the header statements of the source (see `parse_with_header`)
 pub fn to_jedec(
     truth_tables: &Vec<TableData>,
     config: &CircuitConfig,
     head: Option<String>,
+) -> Result<String, String> {
+    to_jedec_with_vectors(truth_tables, config, head, &[])
+}
+
+/// like `to_jedec` but also writes the given test vectors (see `OGal::jedec_vectors`) as `V` fields
+pub fn to_jedec_with_vectors(
+    truth_tables: &Vec<TableData>,
+    config: &CircuitConfig,
+    head: Option<String>,
+    vectors: &[String],
 ) -> Result<String, String> {
     let mut exprs = Vec::new();
     for truth_table in truth_tables {
         exprs.push(Expression::new(truth_table, config)?);
     }
+    let (exprs, _) = split::split(exprs, truth_tables, config)?;
 
     let fuses = fuses::build(&exprs, config)?;
 
-    Ok(jedec::jedec(config.num_pins, config.num_fuses, fuses, head))
+    Ok(jedec::jedec(
+        config.num_pins,
+        config.num_fuses,
+        fuses,
+        head,
+        vectors,
+    ))
 }
@@ -1,4 +1,4 @@
-use crate::{CircuitConfig, TableData};
+use crate::{CircuitConfig, Control, TableData};
 
 #[derive(PartialEq, Debug, Clone)]
 
@@ -29,6 +29,28 @@ impl Row {
 
         Self { pins }
     }
+
+    /// the product term of a cube, inputs whose bit is set in `mask` are left out
+    pub fn from_cube(value: u32, mask: u32, inputs: &[u32]) -> Self {
+        let mut pins = Vec::new();
+        for (i, &pin) in inputs.iter().enumerate() {
+            if mask >> i & 1 == 0 {
+                pins.push(Pin::new(value >> i & 1 == 0, pin));
+            }
+        }
+        Self { pins }
+    }
+
+    /// true if the term is 1 in the row `index` of a truth table over `inputs`, input `i` is
+    /// bit `i` of the index
+    pub fn covers(&self, inputs: &[u32], index: usize) -> bool {
+        self.pins.iter().all(
+            |pin| match inputs.iter().position(|&input| input == pin.pin_num) {
+                Some(i) => (index >> i & 1 == 1) != pin.inverted,
+                None => false,
+            },
+        )
+    }
 }
 
 impl PartialEq for Row {
@@ -47,26 +69,199 @@ pub struct Expression {
     pub out_pin: u32,
     pub enable_flip_flop: bool,
     pub rows: Vec<Row>,
+    /// the product term of every control, `None` if it is never true
+    pub controls: Vec<(Control, Option<Row>)>,
 }
 
 impl Expression {
     pub fn new(truth_table: &TableData, config: &CircuitConfig) -> Result<Self, String> {
         truth_table.valid(config)?;
+        let table = term_table(truth_table, config);
 
         let mut rows = Vec::new();
 
-        for (i, &val) in truth_table.table.clone().iter().enumerate() {
-            if val {
-                let bits = uint_to_bool_vec(i as u32);
-                rows.push(Row::new(bits, truth_table.input_pins.clone()));
+        if truth_table.hazard_free {
+            for (value, mask) in hazard_free_cover(&table) {
+                rows.push(Row::from_cube(value, mask, &truth_table.input_pins));
+            }
+
+            let terms = config
+                .terms(truth_table.output_pin, truth_table.enable_flip_flop)
+                .unwrap_or(0);
+            if rows.len() > terms as usize {
+                return Err(format!(
+                    "pin {} needs {} terms to be hazard free but only has {}",
+                    truth_table.output_pin,
+                    rows.len(),
+                    terms
+                ));
+            }
+        } else {
+            for (i, &val) in table.iter().enumerate() {
+                if val {
+                    let bits = uint_to_bool_vec(i as u32);
+                    rows.push(Row::new(bits, truth_table.input_pins.clone()));
+                }
             }
         }
+
+        let mut controls = Vec::new();
+        for control in truth_table.controls.iter() {
+            if let Some(pin) = control
+                .input_pins
+                .iter()
+                .find(|pin| !config.inputs.contains(pin))
+            {
+                return Err(format!("input pin {} is not defined in config", pin));
+            }
+
+            // a single product term is the only prime implicant of its table
+            let primes = prime_implicants(&control.table);
+            if primes.len() > 1 {
+                return Err(format!(
+                    "the {} of pin {} needs {} product terms but only has one",
+                    control.control.name(),
+                    truth_table.output_pin,
+                    primes.len()
+                ));
+            }
+            let row = primes
+                .first()
+                .map(|&(value, mask)| Row::from_cube(value, mask, &control.input_pins));
+            controls.push((control.control, row));
+        }
+
         Ok(Self {
             out_pin: truth_table.output_pin,
             enable_flip_flop: truth_table.enable_flip_flop,
             rows,
+            controls,
         })
     }
+
+    /// Finds the static-1 hazards of the terms, two true rows of `table` which only differ in
+    /// one input but aren't covered by the same term. The sum of the terms can drop to 0 for a
+    /// moment when that input changes. `table` is the one the terms are built from, see
+    /// `term_table`. Returns the row with the input at 0 and the position of the input.
+    pub fn static_hazards(&self, table: &[bool], inputs: &[u32]) -> Vec<(usize, usize)> {
+        let mut result = Vec::new();
+        for (index, &val) in table.iter().enumerate() {
+            for i in 0..inputs.len() {
+                let other = index | 1 << i;
+                if !val || other == index || !table[other] {
+                    continue;
+                }
+                if !self
+                    .rows
+                    .iter()
+                    .any(|row| row.covers(inputs, index) && row.covers(inputs, other))
+                {
+                    result.push((index, i));
+                }
+            }
+        }
+        result
+    }
+}
+
+/// the table the terms of an output are built from, an active low output gets the inverted table
+pub fn term_table(truth_table: &TableData, config: &CircuitConfig) -> Vec<bool> {
+    let active_low = config
+        .architecture()
+        .olmc(truth_table.output_pin)
+        .and_then(|olmc| olmc.mode(truth_table.enable_flip_flop))
+        .is_some_and(|mode| mode.active_low);
+    truth_table
+        .table
+        .iter()
+        .map(|&val| val != active_low)
+        .collect()
+}
+
+/// All prime implicants of the table as `(value, mask)` where the bits set in `mask` don't
+/// matter (Quine McCluskey). Their sum has a term for every pair of adjacent true rows, the
+/// consensus terms included, so a single changing input can't glitch the output.
+pub fn prime_implicants(table: &[bool]) -> Vec<(u32, u32)> {
+    let mut cubes: Vec<(u32, u32)> = table
+        .iter()
+        .enumerate()
+        .filter(|(_, &val)| val)
+        .map(|(i, _)| (i as u32, 0))
+        .collect();
+    let mut primes = Vec::new();
+
+    while !cubes.is_empty() {
+        let mut merged = vec![false; cubes.len()];
+        let mut next = Vec::new();
+        for i in 0..cubes.len() {
+            for j in i + 1..cubes.len() {
+                let ((a, mask_a), (b, mask_b)) = (cubes[i], cubes[j]);
+                let diff = a ^ b;
+                if mask_a == mask_b && diff.count_ones() == 1 {
+                    merged[i] = true;
+                    merged[j] = true;
+                    let cube = (a & !diff, mask_a | diff);
+                    if !next.contains(&cube) {
+                        next.push(cube);
+                    }
+                }
+            }
+        }
+        for (cube, merged) in cubes.iter().zip(merged) {
+            if !merged {
+                primes.push(*cube);
+            }
+        }
+        cubes = next;
+    }
+
+    primes
+}
+
+/// Picks prime implicants until every true row and every pair of adjacent true rows is covered by
+/// one term. This is the sum of all prime implicants with the terms left out that aren't needed
+/// to be hazard free.
+pub fn hazard_free_cover(table: &[bool]) -> Vec<(u32, u32)> {
+    let primes = prime_implicants(table);
+    let covers = |(value, mask): (u32, u32), index: usize| index as u32 & !mask == value;
+    let bits = (table.len() as f64).log2() as usize;
+
+    // a pair is (row, row) for a row without true neighbours
+    let mut pairs = Vec::new();
+    for (index, &val) in table.iter().enumerate() {
+        if !val {
+            continue;
+        }
+        let mut alone = true;
+        for i in 0..bits {
+            let other = index ^ 1 << i;
+            if table[other] {
+                alone = false;
+                if index < other {
+                    pairs.push((index, other));
+                }
+            }
+        }
+        if alone {
+            pairs.push((index, index));
+        }
+    }
+
+    let mut result = Vec::new();
+    while !pairs.is_empty() {
+        let best = *primes
+            .iter()
+            .max_by_key(|&&prime| {
+                pairs
+                    .iter()
+                    .filter(|&&(a, b)| covers(prime, a) && covers(prime, b))
+                    .count()
+            })
+            .unwrap();
+        pairs.retain(|&(a, b)| !(covers(best, a) && covers(best, b)));
+        result.push(best);
+    }
+    result
 }
 
 fn uint_to_bool_vec(num: u32) -> Vec<bool> {
@@ -127,6 +322,30 @@ mod tests {
         assert_eq!(super::uint_to_bool_vec(std::u32::MAX), vec![true; 32]);
     }
 
+    #[test]
+    fn prime_implicants() {
+        // q = d & g | q & !g with d as bit 2, g as bit 1 and q as bit 0
+        let table: Vec<bool> = (0..8)
+            .map(|i| {
+                let (d, g, q) = (i & 4 != 0, i & 2 != 0, i & 1 != 0);
+                d && g || q && !g
+            })
+            .collect();
+        let mut primes = super::prime_implicants(&table);
+        primes.sort_unstable();
+        // q & !g, d & q and d & g
+        assert_eq!(primes, vec![(1, 4), (5, 2), (6, 1)]);
+
+        let mut cover = super::hazard_free_cover(&table);
+        cover.sort_unstable();
+        assert_eq!(cover, primes);
+
+        // a ^ b has no adjacent rows and needs no extra terms
+        let mut cover = super::hazard_free_cover(&[false, true, true, false]);
+        cover.sort_unstable();
+        assert_eq!(cover, vec![(1, 0), (2, 0)]);
+    }
+
     #[test]
     fn expression_new() {
         let config = super::CircuitConfig::new(
@@ -168,6 +387,7 @@ mod tests {
             super::Expression {
                 out_pin: 23,
                 enable_flip_flop: true,
+                controls: Vec::new(),
                 rows: vec![
                     super::Row {
                         pins: vec![super::Pin::new(true, 3), super::Pin::new(true, 2)],
@@ -183,6 +403,7 @@ mod tests {
             super::Expression {
                 out_pin: 23,
                 enable_flip_flop: true,
+                controls: Vec::new(),
                 rows: vec![super::Row {
                     pins: vec![super::Pin::new(false, 11), super::Pin::new(true, 10)],
                 }],
@@ -190,6 +411,7 @@ mod tests {
             super::Expression {
                 out_pin: 17,
                 enable_flip_flop: false,
+                controls: Vec::new(),
                 rows: vec![super::Row {
                     pins: vec![super::Pin::new(false, 11), super::Pin::new(false, 10)],
                 }],
@@ -197,6 +419,7 @@ mod tests {
             super::Expression {
                 out_pin: 19,
                 enable_flip_flop: false,
+                controls: Vec::new(),
                 rows: vec![
                     super::Row {
                         pins: vec![super::Pin::new(true, 11), super::Pin::new(false, 10)],
@@ -209,6 +432,7 @@ mod tests {
             super::Expression {
                 out_pin: 18,
                 enable_flip_flop: false,
+                controls: Vec::new(),
                 rows: vec![
                     super::Row {
                         pins: vec![super::Pin::new(true, 11), super::Pin::new(false, 10)],
@@ -230,4 +454,28 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn active_low() {
+        use crate::{Device, TableData};
+
+        // y = a & b on an active low output needs the terms of !(a & b)
+        let config = &Device::by_name("PAL16L8").unwrap().config;
+        let table = TableData::new(vec![2, 3], 19, vec![false, false, false, true], false);
+        let expression = super::Expression::new(&table, config).unwrap();
+        assert_eq!(
+            expression.rows,
+            vec![
+                super::Row {
+                    pins: vec![super::Pin::new(true, 2), super::Pin::new(true, 3)],
+                },
+                super::Row {
+                    pins: vec![super::Pin::new(false, 2), super::Pin::new(true, 3)],
+                },
+                super::Row {
+                    pins: vec![super::Pin::new(true, 2), super::Pin::new(false, 3)],
+                },
+            ]
+        );
+    }
 }
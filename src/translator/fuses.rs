@@ -1,239 +1,205 @@
-use crate::translator::dnf::Expression;
-use crate::CircuitConfig;
+use crate::translator::dnf::{Expression, Row};
+use crate::{Architecture, CircuitConfig, Control, Olmc};
 
-/// Fuses::BuildFromExpression generates a fuselist for a specific expression and outputs the result in a supplied
-///	fuselist. It needs to know the term size and number of rows to correctly pad the fuselist with zeroes.
+/// Fuses::BuildFromExpression generates the term rows of the OLMC of an expression, unused terms
+/// stay cleared. The feedback of an output pin depends on the mode of its own OLMC, `modes` holds
+/// every driven output and if it has its flip flop turned on.
 fn build_from_expression(
     expr: &Expression,
-    num_rows: u32,
-    row_len: u32,
-    config: &CircuitConfig,
+    olmc: &Olmc,
+    modes: &[(u32, bool)],
+    architecture: &Architecture,
 ) -> Result<Vec<bool>, String> {
-    if !is_valid(expr.out_pin, &config) {
-        return Err("Expression has invalid output pin".to_string());
-    } else if expr.rows.len() > maximum_terms(expr.out_pin, config)? as usize {
+    if expr.rows.len() > olmc.terms as usize {
         return Err("Too many terms for given output pin".to_string());
     }
 
-    let mut fuse_list = vec![false; (num_rows * row_len) as usize];
-
-    for i in 0..row_len as usize {
-        fuse_list[i] = true;
-    }
+    let row_len = architecture.row_len as usize;
+    let mut fuse_list = vec![false; olmc.terms as usize * row_len];
 
     //	Start writing DNF terms.
-    for term_index in 0..expr.rows.len() {
-        for i in 0..row_len {
-            let index = (row_len + term_index as u32 * row_len + i) as usize;
-            fuse_list[index] = true;
-        }
-
-        for pin_index in 0..expr.rows[term_index].pins.len() {
-            let pin = expr.rows[term_index].pins[pin_index].clone();
+    for (term_index, row) in expr.rows.iter().enumerate() {
+        let term = &mut fuse_list[term_index * row_len..(term_index + 1) * row_len];
+        term.iter_mut().for_each(|fuse| *fuse = true);
+
+        for pin in row.pins.iter() {
+            let column = match architecture.column(pin.pin_num) {
+                Some(column) => column,
+                None => return Err(format!("pin {} can't be used as an input", pin.pin_num)),
+            };
 
-            let mode = if expr.enable_flip_flop {
-                MacrocellMode::ModeRegisteredHigh
-            } else {
-                MacrocellMode::ModeCombinatorialHigh
+            // the feedback of an OLMC is inverted depending on the mode it is operating in
+            let inverted = match architecture.olmc(pin.pin_num) {
+                Some(feedback) => {
+                    let registered = match modes.iter().find(|&&(out, _)| out == pin.pin_num) {
+                        Some(&(_, registered)) => registered,
+                        None => expr.enable_flip_flop,
+                    };
+                    let mode = if registered {
+                        &feedback.registered
+                    } else {
+                        &feedback.combinational
+                    };
+                    pin.inverted != mode.as_ref().is_some_and(|mode| mode.feedback_inverted)
+                }
+                None => pin.inverted,
             };
-            let index = pin_to_index(pin.pin_num, pin.inverted, mode, config)?;
-            let index = row_len as usize + term_index * row_len as usize + index as usize;
-            fuse_list[index] = false;
+            term[(column + inverted as u32) as usize] = false;
         }
     }
 
     Ok(fuse_list)
 }
 
-pub fn build(exprs: &Vec<Expression>, config: &CircuitConfig) -> Result<Vec<bool>, String> {
-    //	Get row length for one DNF term.
-    let row_len = get_row_length(config);
+/// Interprets the architecture of the config (see `CircuitConfig::architecture`). The fixed
+/// fuses are programmed first, then every expression writes the control rows of its OLMC like the
+/// OE row, its terms and programs the mode fuses. The OLMCs which aren't driven are switched to
+/// their input mode.
+pub fn build(exprs: &[Expression], config: &CircuitConfig) -> Result<Vec<bool>, String> {
+    let architecture = config.architecture();
+    architecture.validate(config)?;
+    let row_len = architecture.row_len as usize;
 
     //	Adjust fuselist size to the fuse list size of the integrated circuit.
-    //	Set AR Fuses to zero (we don't need them as of yet)
+    //	The AR and SP rows stay cleared (we don't need them as of yet).
     let mut fuse_out = vec![false; config.num_fuses as usize];
-
-    //	Start writing expressions to FuseList.
-    for expr in exprs.clone() {
-        let expr_start = get_first_fuse_index(expr.out_pin, config)? as usize;
-        let num_rows = maximum_terms(expr.out_pin, config)?;
-
-        let expr_buf = build_from_expression(&expr, num_rows + 1, row_len, config)?;
-
-        //	Copy ExpressionBuffer into the correct target destination in the fuse matrix.
-        for i in 0..expr_buf.len() {
-            fuse_out[i + expr_start] = expr_buf[i];
-        }
+    for &(first, last, value) in architecture.fixed.iter() {
+        fuse_out[first as usize..=last as usize]
+            .iter_mut()
+            .for_each(|fuse| *fuse = value);
     }
 
-    //	Set SP fuses to zero because we also don't need them as of yet.
-    let last_fuse_idx = get_last_fuse_index(config.outputs.first().unwrap().0, config)?;
-    for i in 0..row_len {
-        fuse_out[(i + last_fuse_idx) as usize] = false;
-    }
+    let modes: Vec<(u32, bool)> = exprs
+        .iter()
+        .map(|expr| (expr.out_pin, expr.enable_flip_flop))
+        .collect();
+    // the mode fuses as (fuse, value, pin), OLMCs can share one e.g. the clock select of the two
+    // registers of an ATF750C macrocell
+    let mut programmed: Vec<(u32, bool, u32)> = Vec::new();
 
-    //	Set S0 & S1 fuses.
+    //	Start writing expressions to FuseList.
     for expr in exprs {
-        let mode_fuses = mode_fuse_indices(expr.out_pin, config)?;
-
-        if expr.enable_flip_flop {
-            fuse_out[(mode_fuses.0) as usize] = true;
-            fuse_out[(mode_fuses.1) as usize] = false;
-        } else {
-            fuse_out[(mode_fuses.0) as usize] = true;
-            fuse_out[(mode_fuses.1) as usize] = true;
-        }
-    }
-
-    Ok(fuse_out)
-}
-
-#[derive(PartialEq, Debug, Clone, Copy)]
-enum MacrocellMode {
-    ModeNone,
-    ModeRegisteredHigh,
-    // ModeRegisteredLow,
-    ModeCombinatorialHigh,
-    // ModeCombinatorialLow,
-}
-
-/// Fuses::Output::GetLastFuseIndex returns the last fuse of an OLMC output.
-fn get_last_fuse_index(pin_num: u32, config: &CircuitConfig) -> Result<u32, String> {
-    if !is_valid(pin_num, config) {
-        return Err("Invalid output pin".to_string());
-    }
-
-    Ok(get_first_fuse_index(pin_num, config)?
-        + (maximum_terms(pin_num, config)? + 1) * get_row_length(config))
-}
-
-/// Fuses::Output::IsValid checks if a given pin is an output pin:
-fn is_valid(pin_num: u32, config: &CircuitConfig) -> bool {
-    for (out_pin, _) in config.outputs.clone() {
-        if pin_num == out_pin {
-            return true;
-        }
-    }
-    false
-}
-
-/// Fuses::Output::ModeFuseIndices returns the mode control fuses for a given output pin.
-/// The return value is a boolean which indicates if the fuse pair was written to the given
-/// std::pair reference. The function will only return false if the given pin number is
-/// an input pin who has no OLMC connected and therefore no control mode pin.
-fn mode_fuse_indices(pin_num: u32, config: &CircuitConfig) -> Result<(u32, u32), String> {
-    if !is_valid(pin_num, config) {
-        return Err(String::new());
-    }
-
-    //	Get last fuse.
-    let fuse_index = get_last_fuse_index(config.outputs[0].0, config)? + get_row_length(config);
+        let olmc = match architecture.olmc(expr.out_pin) {
+            Some(olmc) => olmc,
+            None => return Err("Expression has invalid output pin".to_string()),
+        };
+        let mode = match (expr.enable_flip_flop, olmc.mode(expr.enable_flip_flop)) {
+            (_, Some(mode)) => mode,
+            (true, None) => {
+                return Err(format!(
+                    "pin {} can't be registered on this device",
+                    expr.out_pin
+                ))
+            }
+            (false, None) => {
+                return Err(format!(
+                    "pin {} can only be registered on this device",
+                    expr.out_pin
+                ))
+            }
+        };
 
-    //	Get OLMC number.
-    for i in 0..config.outputs.len() {
-        if config.outputs[i].0 == pin_num {
-            let fuses_start = fuse_index + ((config.outputs.len() - 1 - i) as u32) * 2;
-            return Ok((fuses_start, fuses_start + 1));
+        for &control in Control::ALL.iter() {
+            let term = expr
+                .controls
+                .iter()
+                .find(|(c, _)| *c == control)
+                .map(|(_, term)| term.clone());
+            let row = match (olmc.control_row(control), term.is_some()) {
+                (_, true) if control == Control::Oe && mode.external_oe => {
+                    return Err(format!(
+                        "pin {} is enabled by the OE pin on this device and can't have `.oe`",
+                        expr.out_pin
+                    ))
+                }
+                (None, true) => {
+                    return Err(format!(
+                        "pin {} can't have `.{}` on this device",
+                        expr.out_pin,
+                        control.name()
+                    ))
+                }
+                (None, false) => continue,
+                (Some(row), _) => row,
+            };
+            let term = match (term, control) {
+                (Some(term), _) => term,
+                // without `.oe` the output is always enabled
+                (None, Control::Oe) if !mode.external_oe => Some(Row { pins: Vec::new() }),
+                (None, Control::Clk) if expr.enable_flip_flop && config.clock.is_none() => {
+                    return Err(format!(
+                        "pin {} is registered and needs `.clk` on this device",
+                        expr.out_pin
+                    ))
+                }
+                (None, _) => continue,
+            };
+            let control_expr = Expression {
+                out_pin: expr.out_pin,
+                enable_flip_flop: expr.enable_flip_flop,
+                rows: term.into_iter().collect(),
+                controls: Vec::new(),
+            };
+            let control_olmc = Olmc {
+                terms: 1,
+                ..olmc.clone()
+            };
+            let start = row as usize * row_len;
+            let buf = build_from_expression(&control_expr, &control_olmc, &modes, &architecture)?;
+            fuse_out[start..start + row_len].copy_from_slice(&buf);
         }
-    }
-
-    Err(format!("output pin number {} not found in config", pin_num))
-}
 
-/// Fuses::Output::GetFirstFuseIndex returns the first fuse of an OLMC output.
-fn get_first_fuse_index(pin_num: u32, config: &CircuitConfig) -> Result<u32, String> {
-    if !is_valid(pin_num, config) {
-        return Err("Invalid output pin".to_string());
-    }
-
-    let mut fuse_index = get_row_length(config);
-
-    let mut olmc = config.outputs.last().unwrap().0;
-    while olmc > pin_num {
-        fuse_index += (maximum_terms(olmc, config)? + 1) * get_row_length(config);
-        olmc -= 1;
-    }
-    Ok(fuse_index)
-}
-
-/// Fuses::GetRowLength returns the length of one DNF term row.
-fn get_row_length(config: &CircuitConfig) -> u32 {
-    ((config.inputs.len() + config.special_pins.len()) * 2) as u32
-}
-
-/// Fuses::Output::MaximumTerms returns the maximum amount of terms an output OLMC can handle.
-/// if the function return value is -1 it means that the given pin number is not an valid output pin
-/// thus the function can't return a valid term number.
-fn maximum_terms(pin_num: u32, config: &CircuitConfig) -> Result<u32, String> {
-    for out_pin in config.outputs.clone() {
-        if out_pin.0 == pin_num {
-            return Ok(out_pin.1);
+        let (first_row, terms) = olmc.term_rows(expr.enable_flip_flop);
+        let term_olmc = Olmc {
+            first_row,
+            terms,
+            ..olmc.clone()
+        };
+        let expr_buf = build_from_expression(expr, &term_olmc, &modes, &architecture)?;
+        let start = first_row as usize * row_len;
+        fuse_out[start..start + expr_buf.len()].copy_from_slice(&expr_buf);
+
+        let mut fuses = mode.fuses.clone();
+        if expr
+            .controls
+            .iter()
+            .any(|(control, _)| *control == Control::Clk)
+        {
+            for &(fuse, value) in mode.term_clock.iter() {
+                fuses.retain(|&(other, _)| other != fuse);
+                fuses.push((fuse, value));
+            }
         }
-    }
-    Err(format!("output pin number {} not found in config", pin_num))
-}
-
-/// Fuses::PinToIndex converts a PIN to a fuselist row index. It takes in a PIN number and a boolean
-/// which indicates if the PIN is supposed to be inverted. It requires an extra parameter if the given
-/// PIN number correlates to an output pin. This parameter is called "Mode" and is an enum. The parameter
-/// is needed because the output from the OLMC is inverted or not inverted depending on the mode it is
-/// operating in (the mode is set through S0 and S1 fuses) so we need to know the mode to correctly pick
-/// the inverted or non inverted output from the OLMC output.
-///
-/// Note: The "Mode" parameter is ignored if the PIN number correlates to an input pin. If the PIN is an
-/// output pin and the parameter is not set the function will return -1 which is not a valid index.
-///
-/// Note: If the return value is -1 that means that the function couldn't find a valid index for the given
-/// parameters.
-fn pin_to_index(
-    pin_num: u32,
-    inverted: bool,
-    mode: MacrocellMode,
-    config: &CircuitConfig,
-) -> Result<u32, String> {
-    // Handles special pins.
-    for special_pin in config.special_pins.clone() {
-        if special_pin.0 == pin_num {
-            if inverted {
-                return Ok(special_pin.1 + 1);
-            } else {
-                return Ok(special_pin.1);
+        for (fuse, value) in fuses {
+            if let Some(&(_, _, pin)) = programmed
+                .iter()
+                .find(|&&(other, other_value, _)| other == fuse && other_value != value)
+            {
+                return Err(format!(
+                    "pin {} and pin {} need different values of the mode fuse {}",
+                    pin, expr.out_pin, fuse
+                ));
             }
+            programmed.push((fuse, value, expr.out_pin));
+            fuse_out[fuse as usize] = value;
+        }
+        for &row in mode.set_rows.iter() {
+            let start = row as usize * row_len;
+            fuse_out[start..start + row_len]
+                .iter_mut()
+                .for_each(|fuse| *fuse = true);
         }
     }
 
-    // Handles output pins.
-    let fuse_index;
-
-    for i in 0..config.outputs.len() {
-        if config.outputs[i].0 == pin_num {
-            fuse_index = (2 + (config.outputs.len() - 1 - i) * 4) as u32;
-
-            if mode == MacrocellMode::ModeCombinatorialHigh {
-                if inverted {
-                    return Ok(fuse_index + 1);
-                } else {
-                    return Ok(fuse_index);
-                }
-            } else if mode != MacrocellMode::ModeNone {
-                if inverted {
-                    return Ok(fuse_index);
-                } else {
-                    return Ok(fuse_index + 1);
-                }
-            } else {
-                return Err(format!("unexpected mode {:?}", mode));
+    for olmc in architecture.olmcs.iter() {
+        if !modes.iter().any(|&(pin, _)| pin == olmc.pin) {
+            for &(fuse, value) in olmc.input.fuses.iter() {
+                fuse_out[fuse as usize] = value;
             }
         }
     }
 
-    // Handles input pins.
-    fuse_index = (pin_num - 1) * 4;
-    if inverted {
-        return Ok(fuse_index + 1);
-    } else {
-        return Ok(fuse_index);
-    }
+    Ok(fuse_out)
 }
 
 #[cfg(test)]
@@ -272,7 +238,7 @@ mod tests {
             ],
             vec![(13, 42)],
         );
-        assert_eq!(super::maximum_terms(23, &config), Ok(8));
+        assert_eq!(config.architecture().olmc(23).unwrap().terms, 8);
     }
 
     #[test]
@@ -297,7 +263,7 @@ mod tests {
             ],
             vec![(13, 42)],
         );
-        assert_eq!(super::get_row_length(&config), 44);
+        assert_eq!(config.architecture().row_len, 44);
     }
 
     #[test]
@@ -305,6 +271,7 @@ mod tests {
         let expression = super::Expression {
             out_pin: 23,
             enable_flip_flop: true,
+            controls: Vec::new(),
             rows: vec![Row {
                 pins: vec![Pin::new(false, 11), Pin::new(true, 10)],
             }],
@@ -331,21 +298,20 @@ mod tests {
             vec![(13, 42)],
         );
 
-        let row_length = super::get_row_length(&config);
-        let num_rows = super::maximum_terms(expression.out_pin, &config).unwrap();
+        let architecture = config.architecture();
+        let olmc = architecture.olmc(expression.out_pin).unwrap();
 
-        let result =
-            super::build_from_expression(&expression, num_rows + 1, row_length, &config).unwrap();
+        let result = super::build_from_expression(&expression, olmc, &[], &architecture).unwrap();
 
-        assert_eq!(result.len(), 396);
+        assert_eq!(result.len(), 352);
         let bytes = fuses_as_bytes(result);
         assert_eq!(
             bytes,
             vec![
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xB7, 0x00, 0x00, 0x00,
+                0xFF, 0xFF, 0xFF, 0xFF, 0xFB, 0x70, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00,
             ]
         );
     }
@@ -379,6 +345,7 @@ mod tests {
             Expression {
                 out_pin: 23,
                 enable_flip_flop: true,
+                controls: Vec::new(),
                 rows: vec![Row {
                     pins: vec![Pin::new(false, 11), Pin::new(true, 10)],
                 }],
@@ -386,6 +353,7 @@ mod tests {
             Expression {
                 out_pin: 17,
                 enable_flip_flop: false,
+                controls: Vec::new(),
                 rows: vec![Row {
                     pins: vec![Pin::new(false, 11), Pin::new(false, 10)],
                 }],
@@ -393,6 +361,7 @@ mod tests {
             Expression {
                 out_pin: 19,
                 enable_flip_flop: false,
+                controls: Vec::new(),
                 rows: vec![
                     Row {
                         pins: vec![Pin::new(true, 11), Pin::new(false, 10)],
@@ -405,6 +374,7 @@ mod tests {
             Expression {
                 out_pin: 18,
                 enable_flip_flop: false,
+                controls: Vec::new(),
                 rows: vec![
                     Row {
                         pins: vec![Pin::new(true, 11), Pin::new(false, 10)],
@@ -420,6 +390,7 @@ mod tests {
             Expression {
                 out_pin: 23,
                 enable_flip_flop: true,
+                controls: Vec::new(),
                 rows: vec![
                     Row {
                         pins: vec![Pin::new(true, 2), Pin::new(true, 3)],
@@ -435,6 +406,7 @@ mod tests {
             Expression {
                 out_pin: 23,
                 enable_flip_flop: true,
+                controls: Vec::new(),
                 rows: vec![
                     Row {
                         pins: vec![Pin::new(true, 2), Pin::new(false, 3)],
@@ -510,6 +482,297 @@ mod tests {
         );
     }
 
+    #[test]
+    fn gal16v8() {
+        use super::{Control, Expression};
+        use crate::Device;
+
+        let modes = Device::modes("GAL16V8");
+        let (complex, registered) = (&modes[1].config, &modes[2].config);
+        let mut expression = Expression {
+            out_pin: 18,
+            enable_flip_flop: false,
+            controls: vec![(
+                Control::Oe,
+                Some(Row {
+                    pins: vec![Pin::new(true, 2)],
+                }),
+            )],
+            rows: vec![Row {
+                pins: vec![Pin::new(false, 2), Pin::new(false, 3)],
+            }],
+        };
+
+        let fuses = super::build(&[expression.clone()], complex).unwrap();
+        assert_eq!(fuses.len(), 2194);
+        // the OE row 8 reads !a, the first term row a & b
+        let row = |n: usize| fuses[n * 32..(n + 1) * 32].to_vec();
+        let mut term = vec![true; 32];
+        term[1] = false;
+        assert_eq!(row(8), term);
+        let mut term = vec![true; 32];
+        term[0] = false;
+        term[4] = false;
+        assert_eq!(row(9), term);
+        assert_eq!(row(10), vec![false; 32]);
+        // XOR and AC1 of the OLMC, pin 19 is an input, then PTD, SYN and AC0
+        assert_eq!((fuses[2049], fuses[2121]), (true, true));
+        assert_eq!((fuses[2048], fuses[2120]), (false, true));
+        assert!(fuses[2128..2192].iter().all(|&fuse| fuse));
+        assert_eq!((fuses[2192], fuses[2193]), (true, true));
+
+        assert_eq!(
+            super::build(&[expression.clone()], &modes[0].config),
+            Err("pin 18 can't have `.oe` on this device".to_string())
+        );
+        expression.enable_flip_flop = true;
+        assert_eq!(
+            super::build(&[expression.clone()], registered),
+            Err("pin 18 is enabled by the OE pin on this device and can't have `.oe`".to_string())
+        );
+        expression.controls.clear();
+        let fuses = super::build(&[expression.clone()], registered).unwrap();
+        // the register is enabled by pin 11, so its OE row 8 is the first of its 8 terms
+        let row = |n: usize| fuses[n * 32..(n + 1) * 32].to_vec();
+        assert_eq!(row(8), term);
+        assert_eq!(row(9), vec![false; 32]);
+        assert_eq!((fuses[2049], fuses[2121]), (true, false));
+        assert_eq!((fuses[2192], fuses[2193]), (false, true));
+
+        expression.rows = (2..10)
+            .map(|pin| Row {
+                pins: vec![Pin::new(false, pin)],
+            })
+            .collect();
+        let fuses = super::build(&[expression.clone()], registered).unwrap();
+        assert!(fuses[15 * 32..16 * 32].contains(&false));
+        expression.enable_flip_flop = false;
+        assert_eq!(
+            super::build(&[expression], registered),
+            Err("Too many terms for given output pin".to_string())
+        );
+    }
+
+    #[test]
+    fn gal20ra10() {
+        use super::{Control, Expression};
+        use crate::Device;
+
+        let config = &Device::by_name("GAL20RA10").unwrap().config;
+        let mut expression = Expression {
+            out_pin: 22,
+            enable_flip_flop: true,
+            controls: vec![
+                (
+                    Control::Clk,
+                    Some(Row {
+                        pins: vec![Pin::new(false, 2)],
+                    }),
+                ),
+                (
+                    Control::Ar,
+                    Some(Row {
+                        pins: vec![Pin::new(true, 3)],
+                    }),
+                ),
+            ],
+            rows: vec![Row {
+                pins: vec![Pin::new(false, 4)],
+            }],
+        };
+
+        let fuses = super::build(&[expression.clone()], config).unwrap();
+        assert_eq!(fuses.len(), 3274);
+        // OE, CLK, AR and AP are the rows 8 to 11 of the second OLMC, its terms follow
+        let row = |n: usize| fuses[n * 40..(n + 1) * 40].to_vec();
+        let term = |column: usize| {
+            let mut term = vec![true; 40];
+            term[column] = false;
+            term
+        };
+        assert_eq!(row(8), vec![true; 40]);
+        assert_eq!(row(9), term(0));
+        assert_eq!(row(10), term(5));
+        assert_eq!(row(11), vec![false; 40]);
+        assert_eq!(row(12), term(8));
+        assert!(fuses[3201]);
+
+        // a combinational output bypasses the register with AR and AP
+        expression.enable_flip_flop = false;
+        expression.controls.clear();
+        let fuses = super::build(&[expression.clone()], config).unwrap();
+        assert!(fuses[10 * 40..12 * 40].iter().all(|&fuse| fuse));
+
+        expression.enable_flip_flop = true;
+        assert_eq!(
+            super::build(&[expression], config),
+            Err("pin 22 is registered and needs `.clk` on this device".to_string())
+        );
+    }
+
+    #[test]
+    fn atf750c() {
+        use super::{Control, Expression};
+        use crate::Device;
+
+        let config = &Device::by_name("ATF750C").unwrap().config;
+        let clk = (
+            Control::Clk,
+            Some(Row {
+                pins: vec![Pin::new(false, 2)],
+            }),
+        );
+        let register = |pin, controls| Expression {
+            out_pin: pin,
+            enable_flip_flop: true,
+            controls,
+            rows: vec![Row {
+                pins: vec![Pin::new(false, 3)],
+            }],
+        };
+
+        // pin 23 and node 34 are the first macrocell, its clock select fuse is 14366
+        let exprs = [
+            register(23, vec![clk.clone()]),
+            register(34, vec![clk.clone()]),
+        ];
+        let fuses = super::build(&exprs, config).unwrap();
+        assert_eq!(fuses.len(), 14394);
+        assert_eq!(&fuses[14364..14367], &[true, false, false]);
+        let row = |n: usize| fuses[n * 84..(n + 1) * 84].to_vec();
+        let mut term = vec![true; 84];
+        term[2] = false;
+        assert_eq!(row(1), term);
+        assert_eq!(row(7), term);
+
+        let exprs = [register(23, Vec::new()), register(34, Vec::new())];
+        assert!(super::build(&exprs, config).unwrap()[14366]);
+        let exprs = [register(23, Vec::new()), register(34, vec![clk])];
+        assert_eq!(
+            super::build(&exprs, config),
+            Err("pin 23 and pin 34 need different values of the mode fuse 14366".to_string())
+        );
+    }
+
+    #[test]
+    fn gal26cv12() {
+        use super::Expression;
+        use crate::{Architecture, Device};
+
+        let config = &Device::by_name("GAL26CV12").unwrap().config;
+        assert_eq!(config.architecture(), Architecture::derive(config));
+
+        // c = a & b with a on pin 1 and b on pin 2, pin 27 is the first OLMC
+        let expression = Expression {
+            out_pin: 27,
+            enable_flip_flop: false,
+            controls: Vec::new(),
+            rows: vec![Row {
+                pins: vec![Pin::new(false, 1), Pin::new(false, 2)],
+            }],
+        };
+        let fuses = super::build(&[expression], config).unwrap();
+        assert_eq!(fuses.len(), 6432);
+        let row = |n: usize| fuses[n * 52..(n + 1) * 52].to_vec();
+        assert_eq!(row(0), vec![false; 52]);
+        assert_eq!(row(1), vec![true; 52]);
+        let mut term = vec![true; 52];
+        term[0] = false;
+        term[4] = false;
+        assert_eq!(row(2), term);
+        assert_eq!(row(3), vec![false; 52]);
+        assert_eq!(&fuses[6344..6346], &[true, true]);
+        assert!(fuses[6346..].iter().all(|&fuse| !fuse));
+
+        // c = !a | b registered, the architecture fuses of pin 27 clear S1
+        let expression = Expression {
+            out_pin: 27,
+            enable_flip_flop: true,
+            controls: Vec::new(),
+            rows: vec![
+                Row {
+                    pins: vec![Pin::new(true, 1)],
+                },
+                Row {
+                    pins: vec![Pin::new(false, 2)],
+                },
+            ],
+        };
+        let fuses = super::build(&[expression], config).unwrap();
+        let row = |n: usize| fuses[n * 52..(n + 1) * 52].to_vec();
+        assert_eq!(row(1), vec![true; 52]);
+        let mut term = vec![true; 52];
+        term[1] = false;
+        assert_eq!(row(2), term);
+        let mut term = vec![true; 52];
+        term[4] = false;
+        assert_eq!(row(3), term);
+        assert_eq!(row(4), vec![false; 52]);
+        assert_eq!(&fuses[6344..6346], &[true, false]);
+        assert!(fuses[6346..].iter().all(|&fuse| !fuse));
+    }
+
+    #[test]
+    fn pal16r4() {
+        use super::{Control, Expression};
+        use crate::Device;
+
+        // pin 19 is combinational with its OE row 0, pin 17 registered from row 16
+        let config = &Device::by_name("PAL16R4").unwrap().config;
+        let mut exprs = vec![
+            Expression {
+                out_pin: 19,
+                enable_flip_flop: false,
+                controls: Vec::new(),
+                rows: vec![Row {
+                    pins: vec![Pin::new(false, 2)],
+                }],
+            },
+            Expression {
+                out_pin: 17,
+                enable_flip_flop: true,
+                controls: Vec::new(),
+                rows: vec![Row {
+                    pins: vec![Pin::new(true, 3)],
+                }],
+            },
+        ];
+        let fuses = super::build(&exprs, config).unwrap();
+        // no architecture fuses after the rows
+        assert_eq!(fuses.len(), 2048);
+        let row = |n: usize| fuses[n * 32..(n + 1) * 32].to_vec();
+        assert_eq!(row(0), vec![true; 32]);
+        let mut term = vec![true; 32];
+        term[0] = false;
+        assert_eq!(row(1), term);
+        assert_eq!(row(2), vec![false; 32]);
+        let mut term = vec![true; 32];
+        term[5] = false;
+        assert_eq!(row(16), term);
+        assert_eq!(row(17), vec![false; 32]);
+
+        exprs[1].controls.push((
+            Control::Oe,
+            Some(Row {
+                pins: vec![Pin::new(false, 2)],
+            }),
+        ));
+        assert_eq!(
+            super::build(&exprs, config),
+            Err("pin 17 is enabled by the OE pin on this device and can't have `.oe`".to_string())
+        );
+        exprs[1].enable_flip_flop = false;
+        assert_eq!(
+            super::build(&exprs[1..], config),
+            Err("pin 17 can only be registered on this device".to_string())
+        );
+        exprs[0].enable_flip_flop = true;
+        assert_eq!(
+            super::build(&exprs[..1], config),
+            Err("pin 19 can't be registered on this device".to_string())
+        );
+    }
+
     pub fn fuses_as_bytes(fuses: Vec<bool>) -> Vec<u8> {
         let mut byte = vec![false; 8];
         let mut result = Vec::new();
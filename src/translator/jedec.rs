@@ -10,6 +10,7 @@ const ID_DEFAULT_FUSESTATE_FIELD: char = 'F';
 const ID_VALUE: char = 'Q';
 const ID_PIN: char = 'P';
 // const ID_DEVICETYPE: char = 'D';
+const ID_TEST_VECTOR: char = 'V';
 const ID_TERMINATOR: char = '*';
 
 //	Start of text and end of text control characters which are used in JEDEC files.
@@ -24,6 +25,7 @@ pub fn jedec(
     num_fuses: u32,
     fuse_states: Vec<bool>,
     head: Option<String>,
+    vectors: &[String],
 ) -> String {
     let mut result = String::new();
 
@@ -38,9 +40,19 @@ pub fn jedec(
 
     //	Comment section end.
     result.push_str(&format!(
-        "{}{}{}{}\n*{}{}{}\n*G0\n*F",
+        "{}{}{}{}\n*{}{}{}",
         ID_TERMINATOR, ID_VALUE, ID_PIN, num_pins, ID_VALUE, ID_DEFAULT_FUSESTATE_FIELD, num_fuses
     ));
+    if !vectors.is_empty() {
+        result.push_str(&format!(
+            "\n{}{}{}{}",
+            ID_TERMINATOR,
+            ID_VALUE,
+            ID_TEST_VECTOR,
+            vectors.len()
+        ));
+    }
+    result.push_str("\n*G0\n*F");
 
     //	Start writing fusestates to file buffer.
     let mut index = 0;
@@ -84,11 +96,22 @@ pub fn jedec(
     //	Write fuselist checksum to file buffer.
 
     result.push_str(&format!(
-        "\n*C{}\n{}",
-        fill_num(4, &format!("{:X?}", fuse_checksum)),
-        ASCII_CTRL_ETX
+        "\n*C{}\n",
+        fill_num(4, &format!("{:X?}", fuse_checksum))
     ));
 
+    //	Write test vectors.
+    for (i, vector) in vectors.iter().enumerate() {
+        result.push_str(&format!(
+            "{}{}{} {}\n",
+            ID_TERMINATOR,
+            ID_TEST_VECTOR,
+            fill_num(4, &format!("{}", i + 1)),
+            vector
+        ));
+    }
+    result.push(ASCII_CTRL_ETX);
+
     //	Calculate checksum for complete file buffer.
     let file_checksum = 0;
 
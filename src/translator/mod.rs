@@ -1,5 +1,6 @@
 pub mod core;
-mod dnf;
+pub mod dnf;
 mod fuses;
-mod jedec;
+pub mod jedec;
+pub mod split;
 mod utils;
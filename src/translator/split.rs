@@ -0,0 +1,163 @@
+use crate::translator::dnf::{Expression, Pin, Row};
+use crate::{CircuitConfig, TableData};
+
+/// An output with more terms than its OLMC has. The terms which don't fit are moved to the
+/// helper OLMCs and fed back into the output, every helper adds one propagation delay.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Split {
+    pub out_pin: u32,
+    pub helpers: Vec<u32>,
+}
+
+/// Moves the terms which don't fit into the OLMC of their output to unused OLMCs e.g.
+/// `y = p1 + ... + p7 + helper` and `helper = p8 + ... + p12`. The helpers are taken from
+/// `TableData::helper_pins` if they are set, otherwise the unused OLMC with the fewest terms that
+/// still fit is picked. A helper which doesn't fit either all of them is split again.
+pub fn split(
+    exprs: Vec<Expression>,
+    truth_tables: &[TableData],
+    config: &CircuitConfig,
+) -> Result<(Vec<Expression>, Vec<Split>), String> {
+    // pins which are driven, read or locked for a helper can't be used for another one
+    let mut used = Vec::new();
+    for table in truth_tables {
+        used.push(table.output_pin);
+        used.extend(table.input_pins.iter());
+        used.extend(table.helper_pins.iter());
+    }
+
+    let mut result = Vec::new();
+    let mut splits = Vec::new();
+    for (mut expr, table) in exprs.into_iter().zip(truth_tables) {
+        let mut locked = table.helper_pins.iter();
+        let mut split = Split {
+            out_pin: expr.out_pin,
+            helpers: Vec::new(),
+        };
+
+        while expr.rows.len() > maximum_terms(&expr, config)? {
+            let keep = maximum_terms(&expr, config)? - 1;
+            let rest = expr.rows.split_off(keep);
+
+            let helper = match locked.next() {
+                Some(&pin) if feedback(pin, config) => pin,
+                Some(&pin) => {
+                    return Err(format!(
+                        "helper pin {} of pin {} is not an OLMC which can be fed back",
+                        pin, table.output_pin
+                    ))
+                }
+                None if !table.helper_pins.is_empty() => {
+                    return Err(format!(
+                        "pin {} needs more helper OLMCs than the ones locked with `.helper`",
+                        table.output_pin
+                    ))
+                }
+                None => pick(rest.len(), &used, config).ok_or_else(|| {
+                    format!(
+                        "pin {} needs {} terms but only has {} and no OLMC is left to split it",
+                        table.output_pin,
+                        keep + rest.len(),
+                        keep + 1
+                    )
+                })?,
+            };
+            used.push(helper);
+            split.helpers.push(helper);
+
+            // an active low helper outputs the inverted sum of its terms
+            let active_low = config
+                .architecture()
+                .olmc(helper)
+                .and_then(|olmc| olmc.mode(false))
+                .is_some_and(|mode| mode.active_low);
+            expr.rows.push(Row {
+                pins: vec![Pin::new(active_low, helper)],
+            });
+            result.push(expr);
+            expr = Expression {
+                out_pin: helper,
+                enable_flip_flop: false,
+                controls: Vec::new(),
+                rows: rest,
+            };
+        }
+        result.push(expr);
+
+        if !split.helpers.is_empty() {
+            splits.push(split);
+        }
+    }
+
+    Ok((result, splits))
+}
+
+/// the unused OLMC with the fewest terms which still holds `terms`, or the biggest one
+fn pick(terms: usize, used: &[u32], config: &CircuitConfig) -> Option<u32> {
+    let mut free: Vec<(u32, u32)> = config
+        .outputs
+        .iter()
+        .filter(|&&(pin, _)| !used.contains(&pin) && feedback(pin, config))
+        .cloned()
+        .collect();
+    free.sort_by_key(|&(pin, max)| (max, pin));
+
+    free.iter()
+        .find(|&&(_, max)| max as usize >= terms)
+        .or_else(|| free.last())
+        .map(|&(pin, _)| pin)
+}
+
+/// the output of the OLMC can be used as an input and it can be combinational
+fn feedback(pin: u32, config: &CircuitConfig) -> bool {
+    config.outputs.iter().any(|&(out, _)| out == pin)
+        && config.inputs.contains(&pin)
+        && config
+            .architecture()
+            .olmc(pin)
+            .is_some_and(|olmc| olmc.combinational.is_some())
+}
+
+fn maximum_terms(expr: &Expression, config: &CircuitConfig) -> Result<usize, String> {
+    match config.terms(expr.out_pin, expr.enable_flip_flop) {
+        Some(max) => Ok(max as usize),
+        None => Err(format!(
+            "output pin number {} not found in config",
+            expr.out_pin
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split;
+    use crate::translator::dnf::Expression;
+    use crate::{CircuitConfig, TableData};
+
+    #[test]
+    fn split_to_helper() {
+        let config = CircuitConfig::new(
+            100,
+            10,
+            vec![1, 2, 3, 4, 5, 6, 7, 8],
+            vec![(6, 2), (7, 4), (8, 8)],
+            Vec::new(),
+        );
+        // 1 ^ 2 ^ 3 ^ 4 has 8 terms
+        let table: Vec<bool> = (0..16u32).map(|i| i.count_ones() % 2 == 1).collect();
+        let tables = vec![TableData::new(vec![1, 2, 3, 4], 6, table, false)];
+        let exprs = vec![Expression::new(&tables[0], &config).unwrap()];
+
+        let (exprs, splits) = split(exprs, &tables, &config).unwrap();
+        assert_eq!(exprs.len(), 2);
+        assert_eq!(exprs[0].rows.len(), 2);
+        assert_eq!(exprs[1].out_pin, 8);
+        assert_eq!(exprs[1].rows.len(), 7);
+        assert_eq!(splits[0].helpers, vec![8]);
+
+        let mut tables = tables;
+        tables[0].helper_pins = vec![7];
+        let exprs = vec![Expression::new(&tables[0], &config).unwrap()];
+        assert!(split(exprs, &tables, &config).is_err());
+    }
+}
@@ -7,6 +7,7 @@ use std::hash::Hasher;
 const IN_PREFIX: &str = "in_";
 const OUT_PREFIX: &str = "out_";
 
+/// `head` goes on top of the pin definitions, e.g. `Header::wincupl` (see `parse_with_header`)
 pub fn to_wincupl(table_data: &Vec<TableData>, head: Option<&str>) -> String {
     let mut result = if let Some(head) = head {
         format!("{}\n", head)
@@ -37,7 +38,14 @@ fn table_def(table_data: TableData) -> String {
     let mut hasher = DefaultHasher::new();
     let mut result = String::new();
 
-    table_data.hash(&mut hasher);
+    // only the fields which end up in the output so the names stay the same
+    (
+        &table_data.input_pins,
+        table_data.output_pin,
+        &table_data.table,
+        table_data.enable_flip_flop,
+    )
+        .hash(&mut hasher);
     let td_hash = hasher.finish();
 
     result.push_str(&format!("Field {}{}f = [", IN_PREFIX, td_hash));
@@ -81,6 +81,9 @@ mod tests {
             output_pin: 23,
             table: vec![false, false, false, true],
             enable_flip_flop: false,
+            hazard_free: false,
+            helper_pins: Vec::new(),
+            controls: Vec::new(),
         }];
 
         let head = vec![
@@ -142,3 +145,107 @@ mod tests {
         );
     }
 }
+
+#[test]
+fn test_vectors() {
+    use open_gal::{to_jedec_with_vectors, CircuitConfig, OGal};
+
+    let code = r"
+    pin 2, 3 = a, b;
+    pin 23 = y;
+    y = a & b;
+    test { a b -> y; 0 1 -> L; 1 1 -> H; }";
+
+    let config = CircuitConfig::new(
+        5892,
+        24,
+        vec![
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+        ],
+        vec![
+            (14, 8),
+            (15, 10),
+            (16, 12),
+            (17, 14),
+            (18, 16),
+            (19, 16),
+            (20, 14),
+            (21, 12),
+            (22, 10),
+            (23, 8),
+        ],
+        vec![(13, 42)],
+    );
+
+    let vectors = OGal::parse(code).unwrap().jedec_vectors(&config).unwrap();
+    assert_eq!(
+        vectors,
+        vec!["X01XXXXXXXXNXXXXXXXXXXLN", "X11XXXXXXXXNXXXXXXXXXXHN"]
+    );
+
+    let table_data = open_gal::parse(code).unwrap();
+    let jedec = to_jedec_with_vectors(&table_data, &config, None, &vectors).unwrap();
+    let lines: Vec<&str> = jedec.lines().collect();
+    assert_eq!(lines[4], "*QV2");
+    assert_eq!(lines[lines.len() - 3], "*V0001 X01XXXXXXXXNXXXXXXXXXXLN");
+    assert_eq!(lines[lines.len() - 2], "*V0002 X11XXXXXXXXNXXXXXXXXXXHN");
+}
+
+#[test]
+fn load_device_file() {
+    use open_gal::{CircuitConfig, Device};
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Configs/g22v10.json");
+    let expected = Device::by_name("GAL22V10").unwrap().config;
+    assert_eq!(CircuitConfig::load(path), Ok(expected.clone()));
+
+    let json = serde_json::to_string(&expected).unwrap();
+    assert_eq!(CircuitConfig::from_json(&json), Ok(expected));
+
+    let config = |inputs: &str, outputs: &str, special: &str, fuses: u32| {
+        CircuitConfig::from_json(&format!(
+            r#"{{"NumFuses": {}, "TotalNumPins": 8, "InputPins": {}, "OutputPins": {}, "SpecialPins": {}}}"#,
+            fuses, inputs, outputs, special
+        ))
+    };
+    assert!(config("[1, 2, 3]", "[[3, 2]]", "[]", 64).is_ok());
+    assert_eq!(
+        config("[1, 2, 9]", "[[3, 2]]", "[]", 64),
+        Err("pin 9 is out of range, the device has 8 pins".to_string())
+    );
+    assert_eq!(
+        config("[1, 2, 3]", "[[3, 2]]", "[[2, 6]]", 64),
+        Err("pin 2 is both a special pin and an input".to_string())
+    );
+    assert_eq!(
+        config("[1, 2, 3]", "[[3, 2], [3, 2]]", "[]", 64),
+        Err("pin 3 is listed twice as output".to_string())
+    );
+    assert_eq!(
+        config("[1, 2, 3]", "[[3, 2]]", "[]", 31),
+        Err("mode fuse 31 of pin 3 is outside of the 31 fuses".to_string())
+    );
+    assert_eq!(
+        CircuitConfig::from_json(
+            r#"{"NumFuses": 64, "TotalNumPins": 8, "InputPins": [1, 2, 9], "OutputPins": [[9, 2]],
+                "SpecialPins": [], "Nodes": [9]}"#
+        )
+        .map(|config| config.nodes),
+        Ok(vec![9])
+    );
+    assert_eq!(
+        config("[1, 2, 3]", "[[3, 2]]", "[]", 64).map(|mut config| {
+            config.nodes = vec![3];
+            config.validate()
+        }),
+        Ok(Err(
+            "node 3 has the number of a pin, nodes are numbered after the 8 pins".to_string()
+        ))
+    );
+    assert!(CircuitConfig::from_json(r#"{"NumFuses": 64}"#)
+        .unwrap_err()
+        .starts_with("invalid device file: missing field"));
+    assert!(CircuitConfig::load("nope.json")
+        .unwrap_err()
+        .starts_with("can't read nope.json"));
+}
@@ -0,0 +1,127 @@
+use open_gal::lint::check;
+use open_gal::{ParserOptions, Severity};
+
+fn lints(code: &str) -> Vec<(Option<String>, Severity, String)> {
+    check(code, ParserOptions::new())
+        .unwrap()
+        .into_iter()
+        .map(|d| {
+            let span = d.span.unwrap();
+            (d.code, d.severity, code[span.start..span.end].to_string())
+        })
+        .collect()
+}
+
+fn warning(lint: &str, text: &str) -> (Option<String>, Severity, String) {
+    (Some(lint.to_string()), Severity::Warning, text.to_string())
+}
+
+#[test]
+fn lints_warn_by_default() {
+    let code = r"
+    pin 1 = a;
+    pin 2 = b;
+    pin 3 = unused;
+    pin 14 = y;
+    pin 15 = q;
+    pin 16 = r;
+    y = a & b;
+    y |= a & !b;
+    r.dff;
+    q = a & !a;
+    table(a, b -> z).fill(0) {
+        01 1
+        11 1
+        01 0
+    }";
+
+    assert_eq!(
+        lints(code),
+        vec![
+            warning("unused_pin", "unused"),
+            warning("dff_without_equation", "r"),
+            warning("constant_output", "q"),
+            warning("duplicate_rows", "01"),
+        ]
+    );
+}
+
+#[test]
+fn allow_and_deny() {
+    let code = r"
+    #pragma allow(unused_pin)
+    #pragma deny(constant_output)
+    pin 1 = a;
+    pin 2 = unused;
+    pin 14 = y;
+    y = a ^ a;";
+
+    assert_eq!(
+        lints(code),
+        vec![(
+            Some("constant_output".to_string()),
+            Severity::Error,
+            "y".to_string()
+        )]
+    );
+    let err = open_gal::parse(code).unwrap_err();
+    assert!(err.contains("constant_output: y is always 0"));
+
+    assert!(open_gal::parse("#pragma deny(no_such_lint)").is_err());
+}
+
+#[test]
+fn constant_after_merge() {
+    // the table of y has both statements, the lint reports it at the first one
+    let code = "pin 1 = a;\npin 14 = y;\npin 15 = z;\nz = a;\ny = a;\ny |= !a;\n";
+    assert_eq!(lints(code), vec![warning("constant_output", "y")]);
+}
+
+#[test]
+fn multiple_drivers() {
+    let code = "pin 1 = a;\npin 2 = b;\npin 14 = y;\ny = a;\ny = b;\n";
+    let err = check(code, ParserOptions::new()).unwrap_err();
+    let span = err.span.unwrap();
+    assert_eq!(&code[span.start..span.end], "y");
+    assert_eq!(span.start, code.rfind("y = b").unwrap());
+    assert_eq!(err.related.len(), 1);
+    assert_eq!(err.related[0].0.start, code.find("y = a").unwrap());
+    assert!(err
+        .render(code, "a.ogal")
+        .contains("note: first driven here"));
+
+    let err = check("pin 1 = a;\npin 14 = y;\ny |= a;", ParserOptions::new()).unwrap_err();
+    assert!(err.msg.contains("needs an earlier statement"));
+}
+
+#[test]
+fn duplicate_pin() {
+    // the checks of the parser point at the source like the lints do
+    let code = "pin 1 = a;\npin 2 = a;\n";
+    let err = check(code, ParserOptions::new()).unwrap_err();
+    assert_eq!(err.msg, "pin a has been already assigned to 1");
+    assert_eq!(err.span.unwrap().start, code.rfind('a').unwrap());
+    let err = open_gal::parse(code).unwrap_err();
+    assert!(err.contains("2:9: pin a has been already assigned to 1"));
+}
+
+#[test]
+fn combinational_loop() {
+    let code = r"
+    pin 1 = c;
+    pin 2 = d;
+    pin 14 = a;
+    pin 15 = b;
+    a = b & c;
+    b = a | d;";
+
+    let diagnostics = check(code, ParserOptions::new()).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, Some("combinational_loop".to_string()));
+    assert!(diagnostics[0]
+        .msg
+        .contains("a (pin 14) -> b (pin 15) -> a (pin 14)"));
+
+    assert!(lints(&format!("{}\n    b.latch;", code)).is_empty());
+    assert!(lints(&format!("{}\n    b.dff;", code)).is_empty());
+}
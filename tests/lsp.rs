@@ -0,0 +1,169 @@
+use open_gal::lsp::{self, Server};
+use serde_json::{json, Value};
+
+const URI: &str = "file:///tmp/adder.ogal";
+const CODE: &str = "pin 1 = a;\npin 2 = b;\npin 14 = y;\n// sum\ny = a ^ b;\n";
+
+fn open(server: &mut Server, code: &str) -> Value {
+    let mut replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": URI, "languageId": "ogal", "version": 1, "text": code } }
+    }));
+    assert_eq!(replies.len(), 1);
+    replies.remove(0)
+}
+
+fn request(server: &mut Server, method: &str, params: Value) -> Value {
+    let mut replies =
+        server.handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }));
+    assert_eq!(replies.len(), 1);
+    replies.remove(0)
+}
+
+fn at(line: usize, character: usize) -> Value {
+    json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+}
+
+#[test]
+fn diagnostics() {
+    let mut server = Server::new();
+    assert_eq!(open(&mut server, CODE)["params"]["diagnostics"], json!([]));
+
+    let reply = open(&mut server, "pin 1 = a;\ny = a &;\npin = 2;");
+    let diagnostics = reply["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({ "line": 2, "character": 4 })
+    );
+}
+
+#[test]
+fn hover_and_definition() {
+    let mut server = Server::new();
+    open(&mut server, CODE);
+
+    let hover = request(&mut server, "textDocument/hover", at(4, 0));
+    let text = hover["result"]["contents"]["value"].as_str().unwrap();
+    assert!(text.contains("pin 14"));
+    assert!(text.contains("combinational output using 2 product terms"));
+    assert!(text.contains("device: output cell with 8 product terms"));
+
+    let definition = request(&mut server, "textDocument/definition", at(4, 4));
+    assert_eq!(
+        definition["result"]["range"],
+        json!({ "start": { "line": 0, "character": 8 }, "end": { "line": 0, "character": 9 } })
+    );
+
+    // the terms are the ones programmed on the device of the file, a PAL inverts y
+    let mut server = Server::new();
+    open(
+        &mut server,
+        "device = \"PAL16L8\";\npin 2 = a;\npin 3 = b;\npin 19 = y;\ny = a | b;\n",
+    );
+    let hover = request(&mut server, "textDocument/hover", at(4, 0));
+    let text = hover["result"]["contents"]["value"].as_str().unwrap();
+    assert!(text.contains("combinational output using 1 product terms"));
+    assert!(text.contains("device: output cell with 7 product terms"));
+}
+
+#[test]
+fn rename_and_completion() {
+    let mut server = Server::new();
+    open(&mut server, CODE);
+
+    let mut params = at(0, 8);
+    params["newName"] = json!("x");
+    let rename = request(&mut server, "textDocument/rename", params);
+    assert_eq!(
+        rename["result"]["changes"][URI].as_array().unwrap().len(),
+        2
+    );
+    for new_name in ["", "1a", "a b", "pin", "a;"] {
+        let mut params = at(0, 8);
+        params["newName"] = json!(new_name);
+        let rename = request(&mut server, "textDocument/rename", params);
+        assert_eq!(rename["error"]["code"], json!(-32602));
+    }
+    let mut params = at(0, 8);
+    params["newName"] = json!("b");
+    let rename = request(&mut server, "textDocument/rename", params);
+    assert_eq!(rename["error"]["code"], json!(-32803));
+    assert_eq!(
+        rename["error"]["message"],
+        json!("b is already declared at 2:9, renaming a would merge the two signals")
+    );
+    let mut params = at(0, 8);
+    params["newName"] = json!("a");
+    let rename = request(&mut server, "textDocument/rename", params);
+    assert!(rename["result"]["changes"][URI].is_array());
+
+    let completion = request(&mut server, "textDocument/completion", at(5, 0));
+    let labels: Vec<&str> = completion["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(&labels[..4], &["a", "b", "y", "pin"]);
+    assert!(labels.contains(&"dff"));
+}
+
+#[test]
+fn semantic_tokens() {
+    let mut server = Server::new();
+    open(&mut server, "pin 1 = a; // in\n");
+
+    let tokens = request(
+        &mut server,
+        "textDocument/semanticTokens/full",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    assert_eq!(
+        tokens["result"]["data"],
+        json!([0, 0, 3, 0, 0, 0, 4, 1, 2, 0, 0, 2, 1, 5, 0, 0, 2, 1, 1, 0, 0, 3, 5, 3, 0])
+    );
+}
+
+#[test]
+fn stdio() {
+    let body = |msg: Value| {
+        let text = msg.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", text.len(), text)
+    };
+    let input = [
+        body(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })),
+        body(json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" })),
+        body(json!({ "jsonrpc": "2.0", "method": "exit" })),
+    ]
+    .concat();
+
+    let mut output = Vec::new();
+    lsp::run(&mut input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with("Content-Length: "));
+    assert!(output.contains("\"hoverProvider\":true"));
+    assert!(output.contains("\"id\":2"));
+}
+
+#[test]
+fn bad_content_length() {
+    for (header, err) in [
+        (
+            "Content-Length: 100000000\r\n\r\n",
+            "message of 100000000 bytes is larger than 4194304 bytes",
+        ),
+        (
+            "Content-Length: ten\r\n\r\n",
+            "invalid Content-Length \"ten\"",
+        ),
+    ] {
+        let mut output = Vec::new();
+        assert_eq!(
+            lsp::run(&mut header.as_bytes(), &mut output),
+            Err(err.to_string())
+        );
+        assert!(output.is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use hardware_sim::LookupTable;
+use hardware_sim::{Component, LookupTable};
 use open_gal::OGal;
 
 #[test]
@@ -164,3 +164,680 @@ fn table_fill() {
 
     assert_eq!(Ok(o_gal), OGal::parse(code));
 }
+
+#[test]
+fn header() {
+    let code = r#"
+    name = "counter";
+    revision = "02";
+    date = "28/10/2021";
+    pin 1 = a;"#;
+
+    let o_gal = OGal::parse(code).unwrap();
+    let header = o_gal.header();
+
+    assert_eq!(header.name, Some("counter".to_string()));
+    assert_eq!(header.revision, Some("02".to_string()));
+    assert_eq!(header.date, Some("28/10/2021".to_string()));
+    assert_eq!(header.designer, None);
+    assert_eq!(
+        header.wincupl("g22v10"),
+        [
+            "Name     counter ;",
+            "PartNo   None ;",
+            "Date     28/10/2021 ;",
+            "Revision 02 ;",
+            "Designer None ;",
+            "Company  None ;",
+            "Device   g22v10 ;",
+            "",
+        ]
+        .join("\n")
+    );
+    assert!(OGal::parse(r#"name = "a"; name = "b";"#).is_err());
+
+    // the header keys are only keywords at the start of a statement followed by `=`
+    let code = r#"
+    name = "counter";
+    pin 2, 3 = date, device;
+    pin 23 = y;
+    y = date & device;"#;
+    assert!(OGal::parse(code).is_ok());
+    assert!(OGal::parse(r#"name = a;"#).is_err());
+
+    let (tables, header) =
+        open_gal::parse_with_header(code, open_gal::ParserOptions::new()).unwrap();
+    let jedec = open_gal::to_jedec(&tables, &gal22v10(), Some(header.jedec())).unwrap();
+    assert!(jedec.starts_with("\x02\nCreated by open-gal 0.1.0\n*N Name     counter\n*QP24"));
+    let wincupl = open_gal::to_wincupl(&tables, Some(&header.wincupl("g22v10")));
+    assert!(wincupl.starts_with("Name     counter ;\nPartNo   None ;\n"));
+}
+
+#[test]
+fn parser_options() {
+    use open_gal::ParserOptions;
+
+    let expected = OGal::new(vec![("a", 1), ("b", 2), ("c", 3)], Vec::new(), Vec::new());
+
+    let mut options = ParserOptions::new();
+    options.num_first = false;
+    assert_eq!(
+        Ok(expected.clone()),
+        OGal::parse_with("pin a = 1; pin b, c = [2..3];", options)
+    );
+
+    let code = r"
+    pin 1 = a;
+    #pragma name_first
+    pin b, c = 2, 3;";
+    assert_eq!(Ok(expected), OGal::parse(code));
+
+    assert!(OGal::parse("#pragma unknown").is_err());
+}
+
+#[test]
+fn pragma_dialect() {
+    let code = r"
+    #pragma cupl_operators
+    #pragma count_vertical
+    or = i0 # i1;
+    xor = i0 $ i1;
+    table(i0, i1 -> and, or2).count {
+        0001
+        0111
+    }";
+
+    let o_gal = OGal::new(
+        Vec::new(),
+        vec![
+            LookupTable::new(
+                vec![vec![false, true, true, true]],
+                vec!["i0", "i1"],
+                vec!["or"],
+                "",
+            )
+            .unwrap(),
+            LookupTable::new(
+                vec![vec![false, true, true, false]],
+                vec!["i0", "i1"],
+                vec!["xor"],
+                "",
+            )
+            .unwrap(),
+            LookupTable::new(
+                vec![
+                    vec![false, false, false, true],
+                    vec![false, true, true, true],
+                ],
+                vec!["i0", "i1"],
+                vec!["and", "or2"],
+                "",
+            )
+            .unwrap(),
+        ],
+        Vec::new(),
+    );
+
+    assert_eq!(Ok(o_gal), OGal::parse(code));
+    assert!(OGal::parse("or = i0 # i1;").is_err());
+}
+
+#[test]
+fn rom() {
+    use open_gal::ParserOptions;
+    use std::fs;
+
+    let dir = std::env::temp_dir().join("open_gal_rom_test");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("rom.hex"), ":0400000003000201F6\n:00000001FF\n").unwrap();
+    fs::write(dir.join("rom.bin"), [3, 0, 2, 1]).unwrap();
+    fs::write(dir.join("rom.csv"), "3\n0\n2\n1\n").unwrap();
+    fs::write(dir.join("long.csv"), "3\n0\n2\n1\n0\n").unwrap();
+    fs::write(dir.join("wide.csv"), "3\n0\n4\n1\n").unwrap();
+
+    let mut options = ParserOptions::new();
+    options.rom_dir = Some(dir.to_str().unwrap().to_string());
+
+    let o_gal = OGal::new(
+        Vec::new(),
+        vec![LookupTable::new(
+            vec![
+                vec![true, false, false, true],
+                vec![true, true, false, false],
+            ],
+            vec!["a0", "a1"],
+            vec!["d0", "d1"],
+            "",
+        )
+        .unwrap()],
+        Vec::new(),
+    );
+
+    for file in ["rom.hex", "rom.bin", "rom.csv"].iter() {
+        let code = format!("rom(a[0..1] -> d[0..1]) from \"{}\";", file);
+        assert_eq!(Ok(o_gal.clone()), OGal::parse_with(&code, options.clone()));
+    }
+
+    let err = OGal::parse_with(
+        "rom(a[0..1] -> d[0..1]) from \"long.csv\";",
+        options.clone(),
+    );
+    assert!(format!("{:?}", err)
+        .contains("csv line 5: address 0x4 is out of the rom which has 4 words"));
+    let err = OGal::parse_with(
+        "rom(a[0..1] -> d[0..1]) from \"wide.csv\";",
+        options.clone(),
+    );
+    assert!(format!("{:?}", err).contains("doesn't fit into 2 data bits"));
+    // the address width is checked before the table is built, the error points at the rom
+    let err = OGal::parse_with(
+        "pin 1 = y;\nrom(a[0..20] -> d[0..1]) from \"rom.csv\";",
+        options.clone(),
+    );
+    assert!(format!("{:?}", err).contains("2:1: rom address width 21 is larger than 20 bits"));
+    assert!(OGal::parse_with("rom(a[0..1] -> d[0..1]) from \"missing.csv\";", options).is_err());
+
+    // `rom` and `from` are only keywords in a rom statement
+    assert!(OGal::parse("pin 1, 2 = rom, from; pin 23 = y; y = rom & from;").is_ok());
+}
+
+#[test]
+fn format_code() {
+    let code = r"
+// adder
+pin 1=a;   pin 2 = b ; // inputs
+pin [14..15]=s, c;
+
+
+/* outputs */
+s=a^b;
+c = a&
+    b;
+table(a,b->x,y) {
+    0 0   00 // zero
+    01 1 01
+
+    1 0 10
+    11 1 1
+}
+test { a b -> s c;
+    0 0 -> L L;
+    1 1 -> 0 H;
+}";
+
+    let expected = r"// adder
+pin 1 = a;
+pin 2 = b; // inputs
+pin [14..15] = s, c;
+
+/* outputs */
+s = a ^ b;
+c = a & b;
+table(a, b -> x, y) {
+    0  0 00 // zero
+    01 1 01
+    1  0 10
+    11 1 1
+}
+test {
+    a b -> s c;
+    0 0 -> L L;
+    1 1 -> 0 H;
+}
+";
+
+    assert_eq!(Ok(expected.to_string()), open_gal::format_code(code));
+    assert_eq!(Ok(expected.to_string()), open_gal::format_code(expected));
+}
+
+#[test]
+fn to_source() {
+    let code = r#"
+    name = "adder";
+    pin 1 = a;
+    pin 2 = b;
+    pin 14 = s;
+    pin 15 = c;
+    c.dff;
+    s = a ^ b;
+    c = a & b | c;
+    test {
+        a b -> s;
+        1 0 -> 1;
+    }
+    assert always s != 0b1 | a | b;"#;
+
+    let o_gal = OGal::parse(code).unwrap();
+    let source = o_gal.to_source();
+    assert_eq!(Ok(o_gal), OGal::parse(&source));
+    assert_eq!(Ok(source.clone()), open_gal::format_code(&source));
+}
+
+#[test]
+fn output_enable() {
+    use open_gal::Control;
+
+    let code = "pin 2, 3 = a, b; pin 23 = y; y = a; y.oe = a & !b;";
+    let o_gal = OGal::parse(code).unwrap();
+    assert_eq!(o_gal.controls()[0].0, "y");
+    assert_eq!(o_gal.controls()[0].1, Control::Oe);
+    let source = o_gal.to_source();
+    assert!(source.contains("y.oe = a & !b;"));
+    assert_eq!(Ok(o_gal), OGal::parse(&source));
+
+    let tables = open_gal::parse(code).unwrap();
+    assert_eq!(tables[0].controls[0].input_pins, vec![2, 3]);
+    assert!(open_gal::to_jedec(&tables, &gal22v10(), None).is_ok());
+
+    let tables = open_gal::parse("pin 2, 3 = a, b; pin 23 = y; y = a; y.oe = a | b;").unwrap();
+    assert_eq!(
+        open_gal::to_jedec(&tables, &gal22v10(), None),
+        Err("the oe of pin 23 needs 2 product terms but only has one".to_string())
+    );
+    assert!(OGal::parse("y = a; y.oe = a; y.oe = b;").is_err());
+    assert!(open_gal::parse("pin 2 = a; pin 23 = y; y.oe = a;").is_err());
+}
+
+#[test]
+fn register_controls() {
+    use open_gal::Control;
+
+    let code = "pin 2, 3, 4 = a, b, c; pin 22 = q; q.dff; q = a; q.clk = b; q.ar = !c;";
+    let o_gal = OGal::parse(code).unwrap();
+    let controls: Vec<Control> = o_gal.controls().iter().map(|(_, c, _)| *c).collect();
+    assert_eq!(controls, vec![Control::Clk, Control::Ar]);
+    let source = o_gal.to_source();
+    assert!(source.contains("q.clk = b;"));
+    assert_eq!(Ok(o_gal.clone()), OGal::parse(&source));
+    assert_eq!(o_gal.select_device().unwrap().0.name, "GAL20RA10");
+
+    let tables = open_gal::parse(code).unwrap();
+    assert_eq!(
+        open_gal::to_jedec(&tables, &gal22v10(), None),
+        Err("pin 22 can't have `.clk` on this device".to_string())
+    );
+    assert!(open_gal::parse("pin 2 = a; pin 22 = q; q = a; q.ap = a;")
+        .unwrap_err()
+        .contains("q.ap needs a register, add `q.dff;`"));
+}
+
+#[test]
+fn buried_registers() {
+    use open_gal::ast::{Ast, Statement};
+
+    let code = "pin 2 = a; pin 23 = y; node s[0..1].dff; y.dff; s0 = a; s1 = s0; y = s1;";
+    let ast = Ast::parse(code).unwrap();
+    match &ast.statements[2] {
+        Statement::Node(node) => assert!(node.dff && node.names.len() == 2),
+        other => panic!("expected node but got {:?}", other),
+    }
+
+    let mut o_gal = OGal::parse(code).unwrap();
+    assert_eq!(o_gal.nodes(), &vec!["s0".to_string(), "s1".to_string()]);
+    let source = o_gal.to_source();
+    assert!(source.contains("node s0;"));
+    assert_eq!(Ok(o_gal.clone()), OGal::parse(&source));
+
+    let (device, tried) = o_gal.select_device().unwrap();
+    assert_eq!(device.name, "ATF750C");
+    assert_eq!(
+        tried[0].error,
+        Some("s0 is a node but the device has no buried OLMCs".to_string())
+    );
+    assert_eq!(tried.last().unwrap().pins, (2, 24));
+
+    let assigned = o_gal.fit(&device.config).unwrap();
+    assert!(assigned.iter().all(|(_, pin)| (25..=34).contains(pin)));
+    let tables = o_gal.table_data().unwrap();
+    let jedec = open_gal::to_jedec(&tables, &device.config, None).unwrap();
+    assert!(jedec.contains("*QF14394"));
+
+    assert!(OGal::parse("node a; node a;").is_err());
+}
+
+#[test]
+fn ast() {
+    use hardware_sim::Component;
+    use open_gal::ast::{Ast, Name, Span, Statement, Visitor};
+
+    let code = r"
+    pin 1 = a;
+    pin [2..3] = b[0..1];
+    pin 14 = y; // output
+    y = a & b0 | b1;
+    table(a -> z) {
+        00
+        11
+    }
+    z.dff;";
+
+    struct Uses(Vec<Span>);
+    impl Visitor for Uses {
+        fn visit_name(&mut self, name: &Name) {
+            if name.name == "a" {
+                self.0.push(name.span);
+            }
+        }
+    }
+
+    let ast = Ast::parse(code).unwrap();
+    let mut uses = Uses(Vec::new());
+    ast.accept(&mut uses);
+    assert_eq!(uses.0.len(), 3);
+    assert!(uses.0.iter().all(|span| &code[span.start..span.end] == "a"));
+
+    let o_gal = OGal::parse(code).unwrap();
+    let outputs: Vec<Vec<String>> = ast
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Assignment(s) => Some(s.targets.clone()),
+            Statement::Table(s) => Some(s.outputs.clone()),
+            _ => None,
+        })
+        .map(|names| names.into_iter().map(|name| name.name).collect())
+        .collect();
+    let lut_outputs: Vec<Vec<String>> = o_gal.luts().iter().map(|lut| lut.out_names()).collect();
+    assert_eq!(outputs, lut_outputs);
+
+    assert!(matches!(ast.statements[3], Statement::Comment(_)));
+    assert!(Ast::parse("pin 1 = ;").is_err());
+}
+
+#[test]
+fn or_merge() {
+    let o_gal = OGal::parse("pin 1 = a; pin 2 = b; pin 14 = y; y = a; y |= b;").unwrap();
+    assert_eq!(o_gal.luts().len(), 1);
+    assert_eq!(o_gal.luts()[0].in_names(), vec!["a", "b"]);
+    assert_eq!(o_gal.luts()[0].out_names(), vec!["y"]);
+    assert_eq!(
+        o_gal.luts()[0].get_table()[0],
+        vec![false, true, true, true]
+    );
+
+    assert!(OGal::parse("pin 1 = a; pin 2 = b; pin 14 = y; y = a; y = b;").is_err());
+    assert!(OGal::parse("pin 1 = a; pin 14 = y; y |= a;").is_err());
+}
+
+#[test]
+fn latch() {
+    let code = "pin 1 = d; pin 2 = g; pin 14 = q; q.latch(g) = d;";
+    let o_gal = OGal::parse(code).unwrap();
+    assert_eq!(o_gal.latches(), &vec!["q".to_string()]);
+    assert_eq!(o_gal.luts()[0].in_names(), vec!["d", "g", "q"]);
+    assert_eq!(
+        o_gal.luts()[0].get_table()[0],
+        vec![false, true, false, false, false, true, true, true]
+    );
+
+    assert_eq!(
+        open_gal::format_code("q.latch(!g)=d&e;"),
+        Ok("q.latch(!g) = d & e;\n".to_string())
+    );
+
+    let table_data = open_gal::parse(code).unwrap();
+    assert!(table_data[0].hazard_free);
+    assert!(open_gal::lint::check(code, open_gal::ParserOptions::new())
+        .unwrap()
+        .is_empty());
+
+    let config = gal22v10();
+    let report = o_gal.fit_report(&config).unwrap();
+    assert!(report.starts_with("pin 14 q: 3 of 8 terms, combinational, latch\n"));
+    assert_eq!(report.matches("// consensus").count(), 1);
+}
+
+#[test]
+fn hazards() {
+    let code = "pin 1 = a; pin 2 = b; pin 3 = c; pin 14 = y; y = a & b | !a & c;";
+    let o_gal = OGal::parse(code).unwrap();
+    assert!(!o_gal.hazards(&gal22v10()).unwrap().is_empty());
+
+    let o_gal = OGal::parse(&format!("{} y.hazard_free;", code)).unwrap();
+    assert_eq!(o_gal.hazard_free(), &vec!["y".to_string()]);
+    assert!(o_gal.hazards(&gal22v10()).unwrap().is_empty());
+    let report = o_gal.fit_report(&gal22v10()).unwrap();
+    assert!(report.starts_with("pin 14 y: 3 of 8 terms, combinational, hazard free\n"));
+
+    let hazard = open_gal::Hazard {
+        output: "y".to_string(),
+        input: "a".to_string(),
+        levels: vec![("b".to_string(), true), ("c".to_string(), true)],
+    };
+    assert_eq!(
+        hazard.to_string(),
+        "y can glitch when a changes while b = 1, c = 1"
+    );
+}
+
+#[test]
+fn active_low_terms() {
+    // the terms of a PAL output are built from the inverted table, !a & !b has no hazard
+    let code = "pin 2 = a; pin 3 = b; pin 19 = y; y = a | b;";
+    let o_gal = OGal::parse(code).unwrap();
+    let pal16l8 = open_gal::Device::by_name("PAL16L8").unwrap().config;
+    assert!(!o_gal.hazards(&gal22v10()).unwrap().is_empty());
+    assert!(o_gal.hazards(&pal16l8).unwrap().is_empty());
+
+    // y needs one term on a PAL but 15 on a GAL
+    let mut o_gal = OGal::parse("y = a | b | c | d;").unwrap();
+    let assigned = o_gal.fit(&pal16l8).unwrap();
+    assert_eq!(assigned[0], ("y".to_string(), 12));
+    let report = o_gal.fit_report(&pal16l8).unwrap();
+    assert!(report.starts_with("pin 12 y: 1 of 7 terms, combinational\n"));
+}
+
+fn gal22v10() -> open_gal::CircuitConfig {
+    open_gal::Device::by_name("GAL22V10").unwrap().config
+}
+
+#[test]
+fn split_over_helper() {
+    // the 16 terms of a ^ b ^ c ^ d ^ e don't fit into the 8 of pin 14, the rest fits into pin 15
+    let code = "pin 1 = a; pin 2 = b; pin 3 = c; pin 4 = d; pin 5 = e; pin 14 = y;
+    y = a ^ b ^ c ^ d ^ e;";
+    let o_gal = OGal::parse(code).unwrap();
+    let report = o_gal.fit_report(&gal22v10()).unwrap();
+    assert!(report.starts_with(
+        "pin 14 y: 8 of 8 terms, combinational, split over pin 15 (+1 propagation delay)\n"
+    ));
+    assert!(report.contains("pin 15 (helper of y): 9 of 10 terms, combinational\n"));
+
+    let tables = open_gal::parse(code).unwrap();
+    assert!(open_gal::to_jedec(&tables, &gal22v10(), None).is_ok());
+
+    let o_gal = OGal::parse(&format!("{} y.helper(23);", code)).unwrap();
+    assert_eq!(o_gal.helpers(), &vec![("y".to_string(), vec![23])]);
+    assert!(o_gal.fit_report(&gal22v10()).is_err());
+}
+
+#[test]
+fn fit_pins() {
+    let code = "pin 14 = y;\ny = a & b | c;\nz = a ^ b ^ c ^ d;\nq = a;\nq.dff;\n";
+    let mut o_gal = OGal::parse(code).unwrap();
+    let config = gal22v10().with_clock(1);
+    let assigned = o_gal.fit(&config).unwrap();
+    let expected: Vec<(String, usize)> = vec![
+        ("z".to_string(), 23),
+        ("q".to_string(), 15),
+        ("a".to_string(), 2),
+        ("b".to_string(), 3),
+        ("c".to_string(), 4),
+        ("d".to_string(), 5),
+    ];
+    assert_eq!(assigned, expected);
+    assert_eq!(o_gal.pins()["y"], 14);
+    let tables = o_gal.table_data().unwrap();
+    assert!(open_gal::to_jedec(&tables, &config, None).is_ok());
+
+    let code = open_gal::write_pins(code, open_gal::ParserOptions::new(), &assigned).unwrap();
+    assert!(code.starts_with("pin 14 = y;\npin 23 = z;\npin 15 = q;\npin 2 = a;\n"));
+    assert!(open_gal::parse(&code).is_ok());
+}
+
+#[test]
+fn select_device() {
+    let o_gal = OGal::parse("pin 2 = a; y = a & b; z = !a;").unwrap();
+    let (device, tried) = o_gal.select_device().unwrap();
+    assert_eq!(device.full_name(), "GAL16V8 simple");
+    assert_eq!(tried[0].pins, (4, 20));
+    assert_eq!(tried[0].olmcs, (2, 8));
+    assert_eq!(tried[0].terms, (2, 64));
+    assert_eq!(
+        open_gal::utilization_table(&tried),
+        "device              pins   olmcs  terms\nGAL16V8 simple      4/20   2/8    2/64     fits\n"
+    );
+
+    // registers and output enables need the modes which have them
+    let o_gal = OGal::parse("q.dff; q = a & b;").unwrap();
+    let (device, tried) = o_gal.select_device().unwrap();
+    assert_eq!(device.full_name(), "GAL16V8 registered");
+    assert_eq!(
+        tried[0].error,
+        Some("no OLMC left which can register q".to_string())
+    );
+    let o_gal = OGal::parse("y = a; y.oe = b;").unwrap();
+    let (device, tried) = o_gal.select_device().unwrap();
+    assert_eq!(device.full_name(), "GAL16V8 complex");
+    assert_eq!(tried.len(), 2);
+
+    let o_gal = OGal::parse("device = \"ATF20V8B\"; y = a;").unwrap();
+    assert_eq!(
+        o_gal.select_device().unwrap().0.full_name(),
+        "GAL20V8 simple"
+    );
+    let o_gal = OGal::parse("device = \"GAL22V10\"; pin 13 = y; y = a;").unwrap();
+    assert!(o_gal.select_device().is_err());
+    assert_eq!(
+        OGal::parse("device = \"GAL16V9\"; y = a;")
+            .unwrap()
+            .select_device()
+            .map(|(device, _)| device),
+        Err("unknown device GAL16V9".to_string())
+    );
+
+    // the PALs are never picked on their own and their pins have fixed roles
+    let mut o_gal = OGal::parse("device = \"PAL16R8\"; q.dff; q = a & b;").unwrap();
+    let (device, tried) = o_gal.select_device().unwrap();
+    assert_eq!(device.full_name(), "PAL16R8");
+    o_gal.fit(&device.config).unwrap();
+    let jedec = open_gal::to_jedec(&o_gal.table_data().unwrap(), &device.config, None).unwrap();
+    assert!(jedec.contains("*QF2048"));
+    assert_eq!(tried.len(), 1);
+    let o_gal = OGal::parse("device = \"PAL16R8\"; y = a & b;").unwrap();
+    assert!(o_gal.select_device().is_err());
+    let o_gal = OGal::parse("device = \"PAL16R4\"; pin 19 = q; q.dff; q = a;").unwrap();
+    assert!(o_gal.select_device().is_err());
+}
+
+#[test]
+fn select_mode() {
+    use open_gal::Device;
+
+    // `by_name` is the first mode, the design picks the one it needs
+    let gal16v8 = Device::by_name("GAL16V8").unwrap();
+    assert_eq!(gal16v8.full_name(), "GAL16V8 simple");
+    let o_gal = OGal::parse("name = \"counter\"; q.dff; q = a & b;").unwrap();
+    let (device, tried) = o_gal.select_mode(&gal16v8).unwrap();
+    assert_eq!(device.full_name(), "GAL16V8 registered");
+    assert_eq!(tried.len(), 1);
+    let jedec = o_gal.to_jedec(&gal16v8).unwrap();
+    assert!(jedec.contains("*N Name     counter\n"));
+    assert_eq!(jedec, o_gal.to_jedec(&device).unwrap());
+
+    // a register uses its OE row as an eighth term, so it isn't split
+    let o_gal = OGal::parse("q.dff; q = a ^ b ^ c ^ d;").unwrap();
+    let (_, tried) = o_gal.select_mode(&gal16v8).unwrap();
+    assert_eq!((tried[0].olmcs, tried[0].terms), ((1, 8), (8, 64)));
+
+    let o_gal = OGal::parse("y = a; y.oe = b;").unwrap();
+    let (device, _) = o_gal.select_mode(&gal16v8).unwrap();
+    assert_eq!(device.full_name(), "GAL16V8 complex");
+    let o_gal = OGal::parse("y = a; y.oe = b; q.dff; q = a;").unwrap();
+    let (device, _) = o_gal.select_mode(&gal16v8).unwrap();
+    assert_eq!(device.full_name(), "GAL16V8 registered");
+
+    // the registers of the registered mode are enabled by pin 11
+    let o_gal = OGal::parse("q.dff; q = a; q.oe = b;").unwrap();
+    assert_eq!(
+        o_gal.select_mode(&gal16v8).map(|(device, _)| device),
+        Err(
+            "q.dff needs the registered mode but q.oe needs the complex mode of the GAL16V8"
+                .to_string()
+        )
+    );
+    let o_gal = OGal::parse("q.dff; pin 1 = q; q = a;").unwrap();
+    assert_eq!(
+        o_gal.to_jedec(&gal16v8),
+        Err("q.dff needs the registered mode of the GAL16V8 which the design doesn't fit into: output pin 1 is not defined in config".to_string())
+    );
+    let o_gal = OGal::parse("device = \"GAL20V8\"; q.dff; q = a; q.oe = b;").unwrap();
+    assert!(o_gal.select_device().is_err());
+}
+
+#[test]
+fn device_registry() {
+    use open_gal::{Architecture, CircuitConfig, Device, PinRole};
+
+    let device = Device::by_name("atf22v10c").unwrap();
+    assert_eq!(device.name, "GAL22V10");
+    assert_eq!(Device::by_name("PALCE22V10"), Some(device.clone()));
+    assert_eq!(Device::by_name("GAL16V9"), None);
+    assert_eq!(Device::modes("palce16v8").len(), 3);
+    // the device files are parsed once
+    assert!(std::ptr::eq(Device::all(), Device::all()));
+    assert_eq!(
+        Device::by_name("GAL16V8").unwrap().pin_role(15),
+        PinRole::Output(8)
+    );
+
+    // the described architecture is the one the 22V10 formulas give
+    let mut config = device.config.clone();
+    assert_eq!(config.architecture(), Architecture::derive(&config));
+    config.clock = None;
+    config.architecture = None;
+    assert_eq!(
+        config,
+        CircuitConfig::new(
+            5892,
+            24,
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23],
+            vec![
+                (14, 8),
+                (15, 10),
+                (16, 12),
+                (17, 14),
+                (18, 16),
+                (19, 16),
+                (20, 14),
+                (21, 12),
+                (22, 10),
+                (23, 8)
+            ],
+            vec![(13, 42)],
+        )
+    );
+
+    assert_eq!(device.pin_role(1), PinRole::Clock);
+    assert_eq!(device.pin_role(2), PinRole::Input);
+    assert_eq!(device.pin_role(12), PinRole::Gnd);
+    assert_eq!(device.pin_role(13), PinRole::Special(42));
+    assert_eq!(device.pin_role(18), PinRole::Output(16));
+    assert_eq!(device.pin_role(24), PinRole::Vcc);
+
+    let plcc = device.package("plcc28").unwrap();
+    assert_eq!(plcc.pin(1), Some(2));
+    assert_eq!(plcc.pin(12), Some(14));
+    assert_eq!(plcc.pin(13), Some(16));
+    assert_eq!(plcc.pin(24), Some(28));
+    assert_eq!(plcc.pin(25), None);
+    assert_eq!(device.package("DIP24").unwrap().pin(7), Some(7));
+
+    let device = Device::by_name("GAL26CV12").unwrap();
+    assert_eq!(device.pin_role(1), PinRole::Clock);
+    assert_eq!(device.pin_role(7), PinRole::Vcc);
+    assert_eq!(device.pin_role(14), PinRole::Special(24));
+    assert_eq!(device.pin_role(20), PinRole::Output(12));
+    assert_eq!(device.pin_role(21), PinRole::Gnd);
+    assert_eq!(device.package("PLCC28").unwrap().pin(28), Some(28));
+}
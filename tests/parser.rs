@@ -45,24 +45,36 @@ mod tests {
                 output_pin: 17,
                 table: vec![false, false, false, true],
                 enable_flip_flop: false,
+                hazard_free: false,
+                helper_pins: Vec::new(),
+                controls: Vec::new(),
             },
             TableData {
                 input_pins: vec![13, 11],
                 output_pin: 19,
                 table: vec![false, true, true, false],
                 enable_flip_flop: false,
+                hazard_free: false,
+                helper_pins: Vec::new(),
+                controls: Vec::new(),
             },
             TableData {
                 input_pins: vec![13, 11],
                 output_pin: 18,
                 table: vec![false, true, true, true],
                 enable_flip_flop: false,
+                hazard_free: false,
+                helper_pins: Vec::new(),
+                controls: Vec::new(),
             },
             TableData {
                 input_pins: vec![3, 2],
                 output_pin: 23,
                 table: vec![true, true, false, true],
                 enable_flip_flop: true,
+                hazard_free: false,
+                helper_pins: Vec::new(),
+                controls: Vec::new(),
             },
         ];
 
@@ -104,24 +116,36 @@ mod tests {
                 output_pin: 13,
                 table: vec![false, false, false, true],
                 enable_flip_flop: false,
+                hazard_free: false,
+                helper_pins: Vec::new(),
+                controls: Vec::new(),
             },
             TableData {
                 input_pins: vec![1, 2],
                 output_pin: 14,
                 table: vec![false, true, true, true],
                 enable_flip_flop: false,
+                hazard_free: false,
+                helper_pins: Vec::new(),
+                controls: Vec::new(),
             },
             TableData {
                 input_pins: vec![1, 2],
                 output_pin: 15,
                 table: vec![false, true, true, false],
                 enable_flip_flop: false,
+                hazard_free: false,
+                helper_pins: Vec::new(),
+                controls: Vec::new(),
             },
             TableData {
                 input_pins: vec![1],
                 output_pin: 16,
                 table: vec![true, false],
                 enable_flip_flop: false,
+                hazard_free: false,
+                helper_pins: Vec::new(),
+                controls: Vec::new(),
             },
         ];
 
@@ -130,4 +154,127 @@ mod tests {
             assert_eq!(input[i], output[i], "at {}", i);
         }
     }
+
+    #[test]
+    fn test_vectors() {
+        let code = r"
+        pin 1 = clk;
+        pin 2, 3 = a, b;
+        pin 23 = y;
+        pin 22 = q;
+
+        y = a & b;
+        q = !q;
+        q.dff;
+
+        test {
+            clk a b -> y q;
+            0 0 0 -> 0 L;
+            0 1 1 -> 1 L;
+            C 1 0 -> 0 H;
+            C X 0 -> 0 L;
+            C 1 X -> X H;
+        }";
+
+        assert!(parse(code).is_ok());
+
+        let code = r"
+        pin 2, 3 = a, b;
+        pin 23 = y;
+        y = a | b;
+        test { a b -> y; 0 0 -> 0; 1 0 -> 0; }";
+
+        let err = parse(code).unwrap_err();
+        assert!(err.contains("test vector 2 failed: expected y = L but got H"));
+
+        // `test` is only a keyword in front of `{`
+        let code = r"
+        pin 2 = test;
+        pin 23 = y;
+        y = !test;
+        test { test -> y; 0 -> 1; 1 -> 0; }";
+        assert!(parse(code).is_ok());
+    }
+
+    #[test]
+    fn assertions() {
+        let code = r"
+        pin 2, 3 = a15, a14;
+        pin 22, 23 = ram_cs, rom_cs;
+
+        ram_cs = !a15;
+        rom_cs = a15 & a14;
+
+        assert !(ram_cs & rom_cs);";
+
+        assert!(parse(code).is_ok());
+
+        let code = r"
+        pin 2, 3 = a15, a14;
+        pin 22, 23 = ram_cs, rom_cs;
+
+        ram_cs = !a15 | a14;
+        rom_cs = a15 & a14;
+
+        assert !(ram_cs & rom_cs);";
+
+        let err = parse(code).unwrap_err();
+        assert!(err.contains("assert !(ram_cs & rom_cs) doesn't hold for a14 = 1, a15 = 1"));
+
+        // `assert` names a signal when it is assigned
+        let code = r"
+        pin 2 = a;
+        pin 23 = assert;
+        assert = a;
+        assert assert | !a;";
+        assert!(parse(code).is_ok());
+    }
+
+    #[test]
+    fn bounded_model_check() {
+        let counter = r"
+        pin 1 = clk;
+        pin 22, 23 = state[0..1];
+
+        state0 = !state0 & !state1;
+        state1 = state0;
+        state[0..1].dff;
+        ";
+
+        let code = format!(
+            "{}{}",
+            counter, "assert always (state != 0b11); assert eventually (state == 0b10) within 2;"
+        );
+        assert!(parse(&code).is_ok());
+
+        let code = format!(
+            "{}{}",
+            counter, "assert eventually (state == 0b10) within 1;"
+        );
+        let err = parse(&code).unwrap_err();
+        assert!(
+            err.contains("assert eventually (state == 0b10) doesn't hold within 1 clock cycles")
+        );
+        assert!(err.contains("cycle 1: state0 = 1, state1 = 0"));
+
+        let code = r"
+        pin 22, 23 = state[0..1];
+
+        state0 = !state0;
+        state1 = state1 ^ state0;
+        state[0..1].dff;
+
+        assert always (state != 0b11);";
+        let err = parse(code).unwrap_err();
+        assert!(err.contains("assert always (state != 0b11) fails after 3 clock cycles"));
+
+        // `always`, `eventually` and `within` name signals where no keyword fits
+        let code = r"
+        pin 2, 3 = always, within;
+        pin 23 = eventually;
+        eventually = always & within;
+        assert eventually | !(always & within);
+        assert always eventually | !always | !within within 1;";
+        assert!(parse(code).is_ok());
+    }
 }
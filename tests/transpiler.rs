@@ -18,6 +18,9 @@ mod tests {
             output_pin: 23,
             table: vec![false, false, false, true],
             enable_flip_flop: false,
+            hazard_free: false,
+            helper_pins: Vec::new(),
+            controls: Vec::new(),
         }];
 
         let mut out_vec = Vec::new();